@@ -0,0 +1,273 @@
+//! Searches the papers index from a terminal with the same searches and rendering as the bot:
+//! `npaper p2300 n4861`, `npaper search "executors since:2022"`.
+
+use clap::{Args, Parser, Subcommand};
+use wg21_index::implicit_search_request_parser::parse_paper_number;
+use wg21_index::render::{convert_papers_to_result, RenderFormat};
+use wg21_index::{Paper, PaperDatabase, SearchQuery};
+
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    name = "npaper",
+    version,
+    about = "Search C++ proposals in the wg21.link index",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Paper numbers, e.g. P2300, p2300r7 or N4861. Like [P2300] in a chat, a number
+    /// without a revision shows all revisions.
+    numbers: Vec<String>,
+
+    #[command(flatten)]
+    options: Options,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Search papers by a number, a title part or an author name, like /search in the bot.
+    /// Supports since:, until:, year: filters and sort:date.
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+}
+
+#[derive(Args)]
+struct Options {
+    /// Print found papers as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Output format: plain, markdownv2 or html.
+    #[arg(long, global = true, default_value = "plain")]
+    format: RenderFormat,
+
+    /// Number of at most shown papers.
+    #[arg(
+        long,
+        global = true,
+        env = "MAX_RESULTS_PER_REQUEST",
+        default_value_t = 20
+    )]
+    limit: u8,
+
+    /// HTTP(S) URI of the papers index.
+    #[arg(
+        long,
+        global = true,
+        env = "PAPERS_DATABASE_URI",
+        default_value = wg21_index::fetch::WG21_INDEX_URL
+    )]
+    index_uri: url::Url,
+
+    /// Where the downloaded index is kept, by default in the user cache directory.
+    #[arg(long, global = true, env = "NPAPER_CACHE_PATH")]
+    cache_path: Option<PathBuf>,
+
+    /// Download the index again if the cached one is older than this.
+    #[arg(long, global = true, default_value_t = 24)]
+    cache_max_age_in_hours: u64,
+
+    /// Download the index even if the cached one is fresh.
+    #[arg(long, global = true)]
+    refresh: bool,
+
+    /// Never download the index, only use the cached one.
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    offline: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("npaper: {:#}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Returns whether anything is found.
+async fn run(cli: Cli) -> anyhow::Result<bool> {
+    let options = cli.options;
+
+    // Check the request before the index is loaded
+    let request = match cli.command {
+        Some(Command::Search { query }) => Request::Search(query.join(" ").parse()?),
+        None if cli.numbers.is_empty() => {
+            anyhow::bail!("specify paper numbers or a search query, see npaper --help")
+        }
+        None => Request::Numbers(
+            cli.numbers
+                .iter()
+                .map(|number| {
+                    parse_paper_number(number)
+                        .ok_or_else(|| anyhow::anyhow!("{} is not a paper number", number))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+    };
+
+    let paper_database = load_database(&options).await?;
+
+    let (is_result_truncated, papers) = match &request {
+        Request::Numbers(paper_requests) => {
+            paper_database.search_requested_papers(paper_requests, options.limit)
+        }
+        Request::Search(query) => paper_database.search(query, options.limit),
+    };
+
+    print_papers(&papers, &options)?;
+
+    if is_result_truncated {
+        eprintln!(
+            "Only the first {} papers are shown, use a more specific request or --limit",
+            options.limit
+        );
+    }
+
+    Ok(!papers.is_empty())
+}
+
+enum Request {
+    Numbers(Vec<wg21_index::implicit_search_request_parser::ImplicitPaperSearchRequest>),
+    Search(SearchQuery),
+}
+
+fn print_papers(papers: &[&Paper], options: &Options) -> anyhow::Result<()> {
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(papers)?);
+    } else if papers.is_empty() {
+        eprintln!("Nothing is found");
+    } else {
+        println!(
+            "{}",
+            convert_papers_to_result(papers, options.format.renderer())
+        );
+    }
+
+    Ok(())
+}
+
+async fn load_database(options: &Options) -> anyhow::Result<PaperDatabase> {
+    let cache_path = match &options.cache_path {
+        Some(cache_path) => cache_path.clone(),
+        None => default_cache_path()?,
+    };
+    let cache_age = std::fs::metadata(&cache_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok());
+    let max_age = std::time::Duration::from_secs(options.cache_max_age_in_hours * 60 * 60);
+
+    if options.offline || (!options.refresh && is_cache_fresh(cache_age, max_age)) {
+        return read_cache(&cache_path);
+    }
+
+    match wg21_index::fetch::download_index(
+        options.index_uri.clone(),
+        wg21_index::fetch::DEFAULT_DOWNLOAD_TIMEOUT,
+    )
+    .await
+    {
+        Ok(index) => {
+            let mut paper_database = PaperDatabase::from_json(&index)?;
+            paper_database.set_updated_at(chrono::Utc::now());
+
+            // A broken cache only means the index is downloaded again next time
+            if let Err(e) = write_cache(&cache_path, &index) {
+                eprintln!("Cannot save the index to {}: {}", cache_path.display(), e);
+            }
+
+            Ok(paper_database)
+        }
+        Err(e) if cache_age.is_some() => {
+            eprintln!("{}, the cached index is used", e);
+            read_cache(&cache_path)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn is_cache_fresh(cache_age: Option<std::time::Duration>, max_age: std::time::Duration) -> bool {
+    matches!(cache_age, Some(cache_age) if cache_age < max_age)
+}
+
+fn default_cache_path() -> anyhow::Result<PathBuf> {
+    let cache_directory = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(cache_directory) => PathBuf::from(cache_directory),
+        None => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".cache"))
+            .ok_or_else(|| anyhow::anyhow!("cannot find a cache directory, use --cache-path"))?,
+    };
+
+    Ok(cache_directory.join("npaper").join("index.json"))
+}
+
+fn read_cache(cache_path: &std::path::Path) -> anyhow::Result<PaperDatabase> {
+    let index = std::fs::read(cache_path).map_err(|e| {
+        anyhow::anyhow!(
+            "cannot read the cached index {}: {}",
+            cache_path.display(),
+            e
+        )
+    })?;
+
+    Ok(PaperDatabase::from_json(&index)?)
+}
+
+fn write_cache(cache_path: &std::path::Path, index: &[u8]) -> std::io::Result<()> {
+    if let Some(directory) = cache_path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+
+    // Write to a temporary file first, so concurrent runs never read a partially written index
+    let temporary_path = cache_path.with_extension("json.tmp");
+    std::fs::write(&temporary_path, index)?;
+    std::fs::rename(&temporary_path, cache_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{is_cache_fresh, Cli};
+    use clap::Parser;
+    use std::time::Duration;
+
+    #[test]
+    fn test_is_cache_fresh() {
+        let max_age = Duration::from_secs(60 * 60);
+
+        assert!(is_cache_fresh(Some(Duration::from_secs(60)), max_age));
+        assert!(!is_cache_fresh(
+            Some(Duration::from_secs(2 * 60 * 60)),
+            max_age
+        ));
+        assert!(!is_cache_fresh(None, max_age));
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let cli = Cli::try_parse_from(["npaper", "p2300", "N4861", "--json"]).unwrap();
+        assert_eq!(cli.numbers, vec!["p2300", "N4861"]);
+        assert!(cli.command.is_none());
+        assert!(cli.options.json);
+
+        let cli =
+            Cli::try_parse_from(["npaper", "search", "executors", "year:2021", "--limit", "5"])
+                .unwrap();
+        assert!(cli.numbers.is_empty());
+        assert!(cli.command.is_some());
+        assert_eq!(cli.options.limit, 5);
+
+        assert!(Cli::try_parse_from(["npaper", "--refresh", "--offline", "p2300"]).is_err());
+    }
+}
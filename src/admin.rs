@@ -0,0 +1,189 @@
+use teloxide::types::UserId;
+use teloxide::{prelude::*, utils::command::BotCommands};
+
+// Telegram allows bots to send about 30 messages per second to different chats
+const BROADCAST_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Users who are allowed to run admin commands.
+pub type AdminIds = std::sync::Arc<std::collections::HashSet<UserId>>;
+
+// Commands for bot operators. They are not listed in /help and are ignored for other users
+#[derive(Clone, BotCommands)]
+#[command(rename = "snake_case", description = "Admin commands:")]
+pub(crate) enum AdminCommand {
+    #[command(
+        description = "force a papers database update. /reload force accepts an index \
+        which is much smaller than the current database."
+    )]
+    Reload(String),
+    #[command(description = "show bot statistics.")]
+    Stats,
+    #[command(description = "send a message to all known chats.")]
+    Broadcast(String),
+    #[command(description = "ignore all updates from a chat, e.g. /ban_chat -1001234567890.")]
+    BanChat(String),
+    #[command(description = "stop ignoring a chat.")]
+    UnbanChat(String),
+}
+
+pub fn is_admin(msg: &Message, admins: &AdminIds) -> bool {
+    matches!(msg.from(), Some(user) if admins.contains(&user.id))
+}
+
+fn format_reload_result(result: crate::fetch_database::DatabaseUpdateResult) -> String {
+    match result {
+        Ok(report) => format!(
+            "База бумаг обновлена за {:.1} с. Бумаг в базе: {}, добавлено: {}, удалено: {}.",
+            report.duration.as_secs_f64(),
+            report.papers_count,
+            report.added.len(),
+            report.removed
+        ),
+        Err(e) => format!("Не удалось обновить базу бумаг: {}", e),
+    }
+}
+
+pub(crate) async fn admin_command_handler(
+    msg: Message,
+    bot: AutoSend<Bot>,
+    command: AdminCommand,
+    papers: crate::storage::PapersStorage,
+    chat_settings: crate::chat_settings::ChatSettingsStorage,
+    reloader: crate::fetch_database::DatabaseReloader,
+) -> anyhow::Result<()> {
+    let reply = match command {
+        AdminCommand::Reload(arguments) => match arguments.trim() {
+            "" => format_reload_result(reloader.reload().await),
+            "force" => format_reload_result(reloader.force_reload().await),
+            _ => "Неизвестный аргумент. Использование: /reload или /reload force".to_string(),
+        },
+        AdminCommand::Stats => {
+            let (papers_count, updated_at, last_update_error) = {
+                let paper_database = papers.snapshot();
+                (
+                    paper_database.len(),
+                    paper_database.updated_at(),
+                    papers.last_update_error(),
+                )
+            };
+            let statistics = chat_settings.lock().unwrap().statistics();
+
+            let mut reply = format!(
+                "Бумаг в базе: {}\n\
+                 Последнее обновление базы: {}\n\
+                 Известных чатов: {}\n\
+                 Заблокированных чатов: {}\n\
+                 Подписок на бумаги: {}\n\
+                 Получено обновлений с момента запуска: {}",
+                papers_count,
+                updated_at
+                    .map(|updated_at| updated_at.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                    .unwrap_or_else(|| "ещё не было".to_string()),
+                statistics.chats,
+                statistics.banned_chats,
+                statistics.watched_papers,
+                crate::metrics::UPDATES_RECEIVED.get()
+            );
+
+            if let Some(error) = last_update_error {
+                reply.push_str(&format!("\nОшибка последнего обновления базы: {}", error));
+            }
+
+            reply
+        }
+        AdminCommand::Broadcast(text) => {
+            if text.trim().is_empty() {
+                "Укажите текст сообщения: /broadcast текст".to_string()
+            } else {
+                let chats = chat_settings.lock().unwrap().chats();
+                let chat_count = chats.len();
+
+                // Sending takes a while, so the handler doesn't wait for it
+                tokio::spawn(broadcast(bot.clone(), chats, text, msg.chat.id));
+
+                format!(
+                    "Рассылка начата, чатов: {}. Когда она закончится, придёт отчёт.",
+                    chat_count
+                )
+            }
+        }
+        AdminCommand::BanChat(chat_id) => match chat_id.trim().parse::<i64>() {
+            Ok(chat_id) => {
+                chat_settings
+                    .lock()
+                    .unwrap()
+                    .set_banned(ChatId(chat_id), true);
+                log::info!("Chat {} is banned", chat_id);
+                format!("Чат {} заблокирован.", chat_id)
+            }
+            Err(_) => "Укажите идентификатор чата: /ban_chat -1001234567890".to_string(),
+        },
+        AdminCommand::UnbanChat(chat_id) => match chat_id.trim().parse::<i64>() {
+            Ok(chat_id) => {
+                chat_settings
+                    .lock()
+                    .unwrap()
+                    .set_banned(ChatId(chat_id), false);
+                log::info!("Chat {} is unbanned", chat_id);
+                format!("Чат {} разблокирован.", chat_id)
+            }
+            Err(_) => "Укажите идентификатор чата: /unban_chat -1001234567890".to_string(),
+        },
+    };
+
+    bot.send_message(msg.chat.id, reply)
+        .reply_to_message_id(msg.id)
+        .await?;
+
+    Ok(())
+}
+
+/// Sends the text as is to the chats, then reports numbers of successfully sent messages
+/// and failures to `report_chat_id`.
+async fn broadcast(bot: AutoSend<Bot>, chats: Vec<ChatId>, text: String, report_chat_id: ChatId) {
+    let (mut sent, mut failed) = (0, 0);
+
+    for chat_id in chats {
+        match bot.send_message(chat_id, &text).await {
+            Ok(_) => sent += 1,
+            Err(e) => {
+                crate::metrics::SEND_ERRORS.inc();
+                log::warn!("Cannot broadcast a message to chat {}: {}", chat_id, e);
+                failed += 1;
+            }
+        }
+
+        tokio::time::sleep(BROADCAST_DELAY).await;
+    }
+
+    let report = format!(
+        "Рассылка закончена. Отправлено сообщений: {}, ошибок: {}",
+        sent, failed
+    );
+    if let Err(e) = bot.send_message(report_chat_id, report).await {
+        log::warn!("Cannot send a broadcast report: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admin::AdminCommand;
+    use teloxide::utils::command::BotCommands;
+
+    #[test]
+    fn test_parse_admin_command() {
+        assert!(matches!(
+            AdminCommand::parse("/ban_chat -100123", "npaperbot"),
+            Ok(AdminCommand::BanChat(chat_id)) if chat_id == "-100123"
+        ));
+        assert!(matches!(
+            AdminCommand::parse("/reload", "npaperbot"),
+            Ok(AdminCommand::Reload(arguments)) if arguments.is_empty()
+        ));
+        assert!(matches!(
+            AdminCommand::parse("/reload force", "npaperbot"),
+            Ok(AdminCommand::Reload(arguments)) if arguments == "force"
+        ));
+        assert!(AdminCommand::parse("/search x", "npaperbot").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::callbacks::CallbackAction;
+use crate::render::PaperRenderer;
+use crate::storage::{Author, PaperDatabase};
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+// Telegram limits callback data to 64 bytes, "author:" takes 7 of them
+const MAX_CALLBACK_AUTHOR_LENGTH: usize = 56;
+
+const MAX_DISAMBIGUATION_BUTTONS: usize = 10;
+
+/// Renders papers of the author, the most recent first, at most `limit` of them,
+/// with buttons which share the shown papers.
+pub fn render_author_papers(
+    paper_database: &PaperDatabase,
+    author: &Author,
+    renderer: &dyn PaperRenderer,
+    limit: usize,
+    bot_username: &str,
+) -> (String, Option<InlineKeyboardMarkup>) {
+    let papers = paper_database.author_papers(author);
+    let shown_papers = &papers[..papers.len().min(limit)];
+
+    let mut lines = vec![renderer.bold(&format!("{}, бумаг: {}", author.name, papers.len()))];
+    lines.extend(
+        shown_papers
+            .iter()
+            .map(|paper| renderer.render_paper(paper)),
+    );
+
+    if papers.len() > limit {
+        crate::metrics::RESULTS_TRUNCATED.inc();
+        lines.push(renderer.escape(&format!("Показаны только первые {} результатов.", limit)));
+    }
+
+    (
+        lines.join("\n\n"),
+        crate::replies::share_keyboard(shown_papers, bot_username),
+    )
+}
+
+/// Shortens a normalized author name to fit into callback data, respecting char boundaries.
+pub fn callback_author_key(normalized_name: &str) -> String {
+    let mut key = String::new();
+
+    for chr in normalized_name.chars() {
+        if key.len() + chr.len_utf8() > MAX_CALLBACK_AUTHOR_LENGTH {
+            break;
+        }
+        key.push(chr);
+    }
+
+    key
+}
+
+/// Finds an author by a key from callback data, see `callback_author_key`.
+pub fn author_by_callback_key<'a>(
+    paper_database: &'a PaperDatabase,
+    key: &str,
+) -> Option<&'a Author> {
+    paper_database.author(key).or_else(|| {
+        paper_database
+            .find_authors(key)
+            .into_iter()
+            .find(|author| author.normalized_name.starts_with(key))
+    })
+}
+
+pub async fn reply_with_author_papers(
+    bot: &AutoSend<Bot>,
+    msg: &Message,
+    query: &str,
+    papers: &crate::storage::PapersStorage,
+    chat_settings: &crate::chat_settings::ChatSettingsStorage,
+    limit: u8,
+    bot_username: &str,
+) -> anyhow::Result<()> {
+    let renderer = chat_settings.lock().unwrap().format(msg.chat.id).renderer();
+
+    enum Reply {
+        NotFound,
+        Papers(String, Option<InlineKeyboardMarkup>),
+        Disambiguation(String, InlineKeyboardMarkup),
+    }
+
+    let reply = {
+        let _timer = crate::metrics::start_search(crate::metrics::SEARCH_KIND_AUTHOR);
+        let paper_database = papers.snapshot();
+        let authors = paper_database.find_authors(query);
+
+        match authors.as_slice() {
+            [] => Reply::NotFound,
+            [author] => {
+                let (text, keyboard) = render_author_papers(
+                    &paper_database,
+                    author,
+                    renderer,
+                    limit as usize,
+                    bot_username,
+                );
+                Reply::Papers(text, keyboard)
+            }
+            _ => {
+                let buttons = authors
+                    .iter()
+                    .take(MAX_DISAMBIGUATION_BUTTONS)
+                    .map(|author| {
+                        vec![InlineKeyboardButton::callback(
+                            format!("{} ({})", author.name, author.numbers.len()),
+                            CallbackAction::Author(callback_author_key(&author.normalized_name))
+                                .to_string(),
+                        )]
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut text = format!("Найдено авторов: {}. Выберите нужного:", authors.len());
+                if authors.len() > MAX_DISAMBIGUATION_BUTTONS {
+                    text.push_str(&format!(
+                        " (показаны первые {}, уточните запрос)",
+                        MAX_DISAMBIGUATION_BUTTONS
+                    ));
+                }
+
+                Reply::Disambiguation(renderer.escape(&text), InlineKeyboardMarkup::new(buttons))
+            }
+        }
+    };
+
+    match reply {
+        Reply::NotFound => {
+            crate::replies::reply_rendered(
+                bot,
+                msg,
+                renderer.escape("К сожалению, такой автор не найден. Попробуйте другой запрос!"),
+                renderer,
+            )
+            .await?;
+        }
+        Reply::Papers(text, keyboard) => {
+            let request = crate::replies::send_rendered(bot, msg.chat.id, text, renderer)
+                .reply_to_message_id(msg.id);
+
+            match keyboard {
+                Some(keyboard) => request.reply_markup(keyboard).await?,
+                None => request.await?,
+            };
+        }
+        Reply::Disambiguation(text, keyboard) => {
+            crate::replies::send_rendered(bot, msg.chat.id, text, renderer)
+                .reply_to_message_id(msg.id)
+                .reply_markup(keyboard)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::author::{author_by_callback_key, callback_author_key, render_author_papers};
+    use crate::render::RenderFormat;
+    use crate::storage::{Paper, PaperDatabase};
+
+    fn database() -> PaperDatabase {
+        PaperDatabase::new(
+            [
+                ("P1000R0", "John Smith", "2020-01-01"),
+                ("P1001R0", "John Smith", "2021-01-01"),
+                ("P1002R0", "John Smith", "2022-01-01"),
+                ("P2000R0", "Jane Smith", "2022-01-01"),
+            ]
+            .into_iter()
+            .map(|(number, author, date)| {
+                (
+                    number.to_string(),
+                    Paper {
+                        author: Some(author.to_string()),
+                        date: Some(date.to_string()),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect(),
+        )
+    }
+
+    #[test]
+    fn test_render_author_papers() {
+        let database = database();
+        let author = database.author("john smith").unwrap();
+
+        let (text, keyboard) = render_author_papers(
+            &database,
+            author,
+            RenderFormat::Plain.renderer(),
+            2,
+            "npaperbot",
+        );
+        assert_eq!(
+            text,
+            "John Smith, бумаг: 3\n\n\
+             P1002R0 (by John Smith) (2022-01-01)\n\n\
+             P1001R0 (by John Smith) (2021-01-01)\n\n\
+             Показаны только первые 2 результатов."
+        );
+
+        // Only the shown papers are shared
+        let buttons: Vec<_> = keyboard
+            .unwrap()
+            .inline_keyboard
+            .concat()
+            .into_iter()
+            .map(|button| button.text)
+            .collect();
+        assert_eq!(buttons, ["Поделиться P1002R0", "Поделиться P1001R0"]);
+    }
+
+    #[test]
+    fn test_callback_author_key() {
+        assert_eq!(callback_author_key("john smith"), "john smith");
+
+        let long_name = "ł".repeat(40);
+        let key = callback_author_key(&long_name);
+        assert_eq!(key, "ł".repeat(28));
+
+        let database = database();
+        assert_eq!(
+            author_by_callback_key(&database, "jane smith").map(|author| author.name.as_str()),
+            Some("Jane Smith")
+        );
+        assert!(author_by_callback_key(&database, "nobody").is_none());
+    }
+}
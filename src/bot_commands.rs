@@ -0,0 +1,201 @@
+//! Registers commands with Telegram (setMyCommands), so clients show the command menu.
+//! English descriptions are the `BotCommands` ones, Russian descriptions are listed here.
+
+use crate::admin::{AdminCommand, AdminIds};
+use crate::commands::Command;
+use teloxide::prelude::*;
+use teloxide::types::{BotCommand, BotCommandScope, Recipient};
+use teloxide::utils::command::BotCommands;
+
+// Deep links open private chats, so /start makes no sense in groups
+const PRIVATE_ONLY_COMMANDS: &[&str] = &["start"];
+
+fn russian_description(command: &str) -> Option<&'static str> {
+    let description = match command {
+        "start" => {
+            "приветствие и список команд. Ссылки вида t.me/<бот>?start=P2300R7 открывают бумагу"
+        }
+        "help" => "список команд",
+        "about" => "информация о боте",
+        "search" => "поиск бумаги по номеру, части названия или автору",
+        "paper" => "подробная информация о бумаге, например /paper P2300R7",
+        "author" => "бумаги автора, начиная с последних",
+        "mailing" => "бумаги из рассылки, например /mailing 2024-10 LEWG",
+        "format" => "показать или изменить формат ответов",
+        "reload" => {
+            "обновить базу бумаг. /reload force принимает индекс намного меньше текущей базы"
+        }
+        "stats" => "статистика бота",
+        "broadcast" => "отправить сообщение во все известные чаты",
+        "ban_chat" => "игнорировать чат",
+        "unban_chat" => "перестать игнорировать чат",
+        _ => return None,
+    };
+
+    Some(description)
+}
+
+/// Commands shown to users of one language in one scope.
+pub(crate) struct CommandMenu {
+    pub scope: BotCommandScope,
+    /// `None` for users whose language has no dedicated menu.
+    pub language_code: Option<&'static str>,
+    pub commands: Vec<BotCommand>,
+}
+
+/// Menus for all chats, private chats, groups and private chats with admins in every language.
+pub(crate) fn command_menus(admins: &AdminIds) -> Vec<CommandMenu> {
+    let private_commands = menu_commands::<Command>();
+    let group_commands: Vec<BotCommand> = private_commands
+        .iter()
+        .filter(|command| !PRIVATE_ONLY_COMMANDS.contains(&command.command.as_str()))
+        .cloned()
+        .collect();
+    let admin_commands: Vec<BotCommand> = private_commands
+        .iter()
+        .cloned()
+        .chain(menu_commands::<AdminCommand>())
+        .collect();
+
+    let mut scoped_commands = vec![
+        (BotCommandScope::Default, group_commands.clone()),
+        (BotCommandScope::AllPrivateChats, private_commands),
+        (BotCommandScope::AllGroupChats, group_commands),
+    ];
+
+    // A chat with a user has the same id as the user
+    let mut admins: Vec<UserId> = admins.iter().copied().collect();
+    admins.sort_by_key(|admin| admin.0);
+    scoped_commands.extend(admins.into_iter().map(|admin| {
+        (
+            BotCommandScope::Chat {
+                chat_id: Recipient::Id(ChatId(admin.0 as i64)),
+            },
+            admin_commands.clone(),
+        )
+    }));
+
+    scoped_commands
+        .into_iter()
+        .flat_map(|(scope, commands)| {
+            let russian_commands = localize(&commands, russian_description);
+
+            [
+                CommandMenu {
+                    scope: scope.clone(),
+                    language_code: None,
+                    commands,
+                },
+                CommandMenu {
+                    scope,
+                    language_code: Some("ru"),
+                    commands: russian_commands,
+                },
+            ]
+        })
+        .collect()
+}
+
+// teloxide prefixes command names with "/", but Telegram accepts only letters, digits and "_"
+fn menu_commands<C: BotCommands>() -> Vec<BotCommand> {
+    C::bot_commands()
+        .into_iter()
+        .map(|command| BotCommand {
+            command: command.command.trim_start_matches('/').to_string(),
+            ..command
+        })
+        .collect()
+}
+
+// Commands without a translation keep the English description
+fn localize(
+    commands: &[BotCommand],
+    description: fn(&str) -> Option<&'static str>,
+) -> Vec<BotCommand> {
+    commands
+        .iter()
+        .map(|command| match description(&command.command) {
+            Some(description) => BotCommand::new(command.command.clone(), description),
+            None => command.clone(),
+        })
+        .collect()
+}
+
+/// Replaces the command menus registered by the previous start of the bot.
+pub async fn register_commands(
+    bot: &AutoSend<Bot>,
+    admins: &AdminIds,
+) -> Result<(), teloxide::RequestError> {
+    for menu in command_menus(admins) {
+        let mut request = bot.set_my_commands(menu.commands).scope(menu.scope);
+        if let Some(language_code) = menu.language_code {
+            request = request.language_code(language_code);
+        }
+
+        request.await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admin::{AdminCommand, AdminIds};
+    use crate::bot_commands::{command_menus, menu_commands, russian_description};
+    use crate::commands::Command;
+    use teloxide::types::{BotCommandScope, ChatId, Recipient, UserId};
+
+    fn command_names(scope: &BotCommandScope, language_code: Option<&str>) -> Vec<String> {
+        command_menus(&AdminIds::new([UserId(1)].into_iter().collect()))
+            .into_iter()
+            .find(|menu| &menu.scope == scope && menu.language_code == language_code)
+            .unwrap()
+            .commands
+            .into_iter()
+            .map(|command| command.command)
+            .collect()
+    }
+
+    #[test]
+    fn test_every_command_is_translated() {
+        for command in menu_commands::<Command>()
+            .into_iter()
+            .chain(menu_commands::<AdminCommand>())
+        {
+            assert!(
+                russian_description(&command.command).is_some(),
+                "/{} has no Russian description",
+                command.command
+            );
+        }
+    }
+
+    #[test]
+    fn test_command_menus() {
+        let admins = AdminIds::new([UserId(1), UserId(2)].into_iter().collect());
+        // Default, private chats, groups and two admins in two languages
+        assert_eq!(command_menus(&admins).len(), 10);
+
+        let private_commands = command_names(&BotCommandScope::AllPrivateChats, Some("ru"));
+        assert!(private_commands.contains(&"start".to_string()));
+        assert!(private_commands.contains(&"search".to_string()));
+        assert!(!private_commands.contains(&"stats".to_string()));
+
+        let group_commands = command_names(&BotCommandScope::AllGroupChats, None);
+        assert!(!group_commands.contains(&"start".to_string()));
+        assert!(group_commands.contains(&"search".to_string()));
+        assert_eq!(
+            command_names(&BotCommandScope::Default, None),
+            group_commands
+        );
+
+        let admin_commands = command_names(
+            &BotCommandScope::Chat {
+                chat_id: Recipient::Id(ChatId(1)),
+            },
+            None,
+        );
+        assert!(admin_commands.contains(&"start".to_string()));
+        assert!(admin_commands.contains(&"ban_chat".to_string()));
+    }
+}
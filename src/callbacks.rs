@@ -0,0 +1,189 @@
+use crate::paper_card::PaperCardRenderer;
+use std::str::FromStr;
+use teloxide::prelude::*;
+
+/// Actions attached to inline keyboard buttons. Serialized into callback data as
+/// "action:argument", so the whole string has to fit into 64 bytes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CallbackAction {
+    History(String),
+    Watch(String),
+    Mailing(crate::mailing::MailingPage),
+    /// Shows papers of an author chosen from several matching ones.
+    Author(String),
+}
+
+impl std::fmt::Display for CallbackAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallbackAction::History(base_number) => write!(f, "history:{}", base_number),
+            CallbackAction::Watch(base_number) => write!(f, "watch:{}", base_number),
+            CallbackAction::Mailing(page) => write!(f, "mailing:{}", page),
+            CallbackAction::Author(key) => write!(f, "author:{}", key),
+        }
+    }
+}
+
+impl FromStr for CallbackAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (action, argument) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Callback data without an action: {}", s))?;
+
+        match action {
+            "history" => Ok(CallbackAction::History(argument.to_string())),
+            "watch" => Ok(CallbackAction::Watch(argument.to_string())),
+            "mailing" => Ok(CallbackAction::Mailing(argument.parse()?)),
+            "author" => Ok(CallbackAction::Author(argument.to_string())),
+            _ => Err(anyhow::anyhow!("Unknown callback action: {}", action)),
+        }
+    }
+}
+
+pub(crate) async fn callback_handler(
+    query: CallbackQuery,
+    bot: AutoSend<Bot>,
+    papers: crate::storage::PapersStorage,
+    chat_settings: crate::chat_settings::ChatSettingsStorage,
+    limit: u8,
+    me: teloxide::types::Me,
+) -> anyhow::Result<()> {
+    let action = query
+        .data
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("Callback query without data"))?
+        .parse::<CallbackAction>();
+
+    let (chat_id, message_id) = match &query.message {
+        Some(message) => (message.chat.id, message.id),
+        None => {
+            bot.answer_callback_query(query.id).await?;
+            return Ok(());
+        }
+    };
+
+    match action {
+        Ok(CallbackAction::History(base_number)) => {
+            let format = chat_settings.lock().unwrap().format(chat_id);
+            let renderer = format.renderer();
+
+            let text = {
+                let paper_database = papers.snapshot();
+                let revisions = paper_database.revisions(&base_number);
+
+                if revisions.is_empty() {
+                    renderer.escape("История ревизий для этой бумаги не найдена")
+                } else {
+                    renderer.render_history(&base_number, &revisions)
+                }
+            };
+
+            crate::replies::send_rendered(&bot, chat_id, text, renderer).await?;
+
+            bot.answer_callback_query(query.id).await?;
+        }
+        Ok(CallbackAction::Watch(base_number)) => {
+            let is_watched = chat_settings
+                .lock()
+                .unwrap()
+                .toggle_watch(chat_id, &base_number);
+
+            let text = if is_watched {
+                format!(
+                    "Теперь вы будете получать уведомления о новых ревизиях {}",
+                    base_number
+                )
+            } else {
+                format!(
+                    "Вы больше не будете получать уведомления о новых ревизиях {}",
+                    base_number
+                )
+            };
+
+            bot.answer_callback_query(query.id).text(text).await?;
+        }
+        Ok(CallbackAction::Mailing(page)) => {
+            let renderer = chat_settings.lock().unwrap().format(chat_id).renderer();
+
+            let page = page.render(&papers.snapshot(), renderer, limit as usize, me.username());
+
+            if let Some((text, keyboard)) = page {
+                let mut request = bot
+                    .edit_message_text(chat_id, message_id, text)
+                    .reply_markup(keyboard);
+                if let Some(parse_mode) = crate::replies::parse_mode(renderer.format()) {
+                    request = request.parse_mode(parse_mode);
+                }
+                request.await?;
+            }
+
+            bot.answer_callback_query(query.id).await?;
+        }
+        Ok(CallbackAction::Author(key)) => {
+            let renderer = chat_settings.lock().unwrap().format(chat_id).renderer();
+
+            let author_papers = {
+                let paper_database = papers.snapshot();
+
+                crate::author::author_by_callback_key(&paper_database, &key).map(|author| {
+                    crate::author::render_author_papers(
+                        &paper_database,
+                        author,
+                        renderer,
+                        limit as usize,
+                        me.username(),
+                    )
+                })
+            };
+
+            if let Some((text, keyboard)) = author_papers {
+                let request = crate::replies::send_rendered(&bot, chat_id, text, renderer);
+
+                match keyboard {
+                    Some(keyboard) => request.reply_markup(keyboard).await?,
+                    None => request.await?,
+                };
+            }
+
+            bot.answer_callback_query(query.id).await?;
+        }
+        Err(err) => {
+            log::warn!("Cannot handle a callback query: {}", err);
+            bot.answer_callback_query(query.id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::callbacks::CallbackAction;
+
+    #[test]
+    fn test_callback_action_round_trip() {
+        for action in [
+            CallbackAction::History("P2300".to_string()),
+            CallbackAction::Watch("P2300".to_string()),
+            CallbackAction::Mailing(crate::mailing::MailingPage {
+                mailing: Some("2024-10".to_string()),
+                subgroup: None,
+                page: 1,
+            }),
+            CallbackAction::Author("john smith".to_string()),
+        ] {
+            assert_eq!(
+                action.to_string().parse::<CallbackAction>().unwrap(),
+                action
+            );
+        }
+    }
+
+    #[test]
+    fn test_callback_action_invalid() {
+        assert!("history".parse::<CallbackAction>().is_err());
+        assert!("unknown:P2300".parse::<CallbackAction>().is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Per-chat settings: output format, watched papers and bans, stored as a JSON object keyed by
+//! chat id. Changes are written to a temporary file which then replaces the settings file, so a
+//! crash never leaves a partially written file. The persister writes after every change it is
+//! notified about and once more on shutdown.
+
+use crate::render::RenderFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use teloxide::types::ChatId;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ChatSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<RenderFormat>,
+    /// Base numbers (like "P2300") of papers the chat is notified about.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub watched_papers: BTreeSet<String>,
+    /// Updates from banned chats are ignored.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_banned: bool,
+}
+
+/// Summary shown by the /stats admin command.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ChatStatistics {
+    pub chats: usize,
+    pub banned_chats: usize,
+    pub watched_papers: usize,
+}
+
+/// Per-chat preferences, optionally persisted to a JSON file. Changes are only marked here,
+/// [`persist_changes_thread`] writes them to the file outside of the settings lock.
+pub struct ChatSettingsDatabase {
+    settings: HashMap<i64, ChatSettings>,
+    default_format: RenderFormat,
+    path: Option<std::path::PathBuf>,
+    has_unsaved_changes: bool,
+    changed: std::sync::Arc<tokio::sync::Notify>,
+}
+
+impl ChatSettingsDatabase {
+    pub fn new(default_format: RenderFormat, path: Option<std::path::PathBuf>) -> Self {
+        ChatSettingsDatabase {
+            settings: HashMap::new(),
+            default_format,
+            path,
+            has_unsaved_changes: false,
+            changed: Default::default(),
+        }
+    }
+
+    /// Loads settings from `path` if the file exists, otherwise starts with empty settings.
+    pub fn load(
+        default_format: RenderFormat,
+        path: Option<std::path::PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let mut database = Self::new(default_format, path);
+
+        if let Some(path) = &database.path {
+            if path.exists() {
+                let content = std::fs::read_to_string(path)?;
+                database.settings = serde_json::from_str(&content)?;
+            }
+        }
+
+        Ok(database)
+    }
+
+    pub fn default_format(&self) -> RenderFormat {
+        self.default_format
+    }
+
+    pub fn format(&self, chat_id: ChatId) -> RenderFormat {
+        self.settings
+            .get(&chat_id.0)
+            .and_then(|settings| settings.format)
+            .unwrap_or(self.default_format)
+    }
+
+    pub fn set_format(&mut self, chat_id: ChatId, format: RenderFormat) {
+        self.settings.entry(chat_id.0).or_default().format = Some(format);
+        self.mark_changed();
+    }
+
+    /// Subscribes the chat to new revisions of a paper or unsubscribes it if it is
+    /// already subscribed. Returns whether the chat is subscribed after the call.
+    pub fn toggle_watch(&mut self, chat_id: ChatId, base_number: &str) -> bool {
+        let watched_papers = &mut self.settings.entry(chat_id.0).or_default().watched_papers;
+        let base_number = base_number.to_uppercase();
+
+        let is_watched = if watched_papers.remove(&base_number) {
+            false
+        } else {
+            watched_papers.insert(base_number);
+            true
+        };
+
+        self.mark_changed();
+        is_watched
+    }
+
+    pub fn watchers(&self, base_number: &str) -> Vec<ChatId> {
+        let base_number = base_number.to_uppercase();
+
+        self.settings
+            .iter()
+            .filter(|(_, settings)| settings.watched_papers.contains(&base_number))
+            .map(|(chat_id, _)| ChatId(*chat_id))
+            .collect()
+    }
+
+    /// Remembers the chat, so it receives broadcasts. Settings change only for new chats.
+    pub fn register_chat(&mut self, chat_id: ChatId) {
+        if self.settings.contains_key(&chat_id.0) {
+            return;
+        }
+
+        self.settings.insert(chat_id.0, ChatSettings::default());
+        self.mark_changed();
+    }
+
+    pub fn is_banned(&self, chat_id: ChatId) -> bool {
+        self.settings
+            .get(&chat_id.0)
+            .map(|settings| settings.is_banned)
+            .unwrap_or(false)
+    }
+
+    pub fn set_banned(&mut self, chat_id: ChatId, is_banned: bool) {
+        self.settings.entry(chat_id.0).or_default().is_banned = is_banned;
+        self.mark_changed();
+    }
+
+    /// Known chats which aren't banned.
+    pub fn chats(&self) -> Vec<ChatId> {
+        self.settings
+            .iter()
+            .filter(|(_, settings)| !settings.is_banned)
+            .map(|(chat_id, _)| ChatId(*chat_id))
+            .collect()
+    }
+
+    pub fn statistics(&self) -> ChatStatistics {
+        ChatStatistics {
+            chats: self.settings.len(),
+            banned_chats: self
+                .settings
+                .values()
+                .filter(|settings| settings.is_banned)
+                .count(),
+            watched_papers: self
+                .settings
+                .values()
+                .map(|settings| settings.watched_papers.len())
+                .sum(),
+        }
+    }
+
+    fn mark_changed(&mut self) {
+        if self.path.is_some() {
+            self.has_unsaved_changes = true;
+            self.changed.notify_one();
+        }
+    }
+
+    /// Serializes settings if they have changed since the previous call. Returns the file
+    /// path and its new content.
+    fn take_changes(&mut self) -> anyhow::Result<Option<(std::path::PathBuf, String)>> {
+        match &self.path {
+            Some(path) if self.has_unsaved_changes => {
+                let content = serde_json::to_string(&self.settings)?;
+                self.has_unsaved_changes = false;
+                Ok(Some((path.clone(), content)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+pub type ChatSettingsStorage =
+    std::sync::Arc<std::sync::Mutex<crate::chat_settings::ChatSettingsDatabase>>;
+
+/// Writes changed settings to the configured file. Only serialization happens under the lock,
+/// the file is written on the blocking thread pool.
+pub async fn persist_changes(chat_settings: &ChatSettingsStorage) -> anyhow::Result<()> {
+    let changes = chat_settings.lock().unwrap().take_changes()?;
+
+    if let Some((path, content)) = changes {
+        let written = tokio::task::spawn_blocking(move || {
+            let tmp_path = path.with_extension("tmp");
+            std::fs::write(&tmp_path, content)?;
+            std::fs::rename(&tmp_path, path)
+        })
+        .await?;
+
+        if let Err(e) = written {
+            // Retry with the next change or on shutdown
+            chat_settings.lock().unwrap().has_unsaved_changes = true;
+            return Err(e.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Persists settings after every change until cancelled, then persists the last changes.
+pub async fn persist_changes_thread(
+    chat_settings: ChatSettingsStorage,
+    cancellation: tokio_util::sync::CancellationToken,
+) {
+    let changed = chat_settings.lock().unwrap().changed.clone();
+
+    loop {
+        let is_cancelled = tokio::select! {
+            _ = changed.notified() => false,
+            _ = cancellation.cancelled() => true,
+        };
+
+        if let Err(e) = persist_changes(&chat_settings).await {
+            log::error!("Cannot persist chat settings: {}", e);
+        }
+
+        if is_cancelled {
+            log::info!("Chat settings persister is stopped");
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chat_settings::{persist_changes, ChatSettingsDatabase, ChatStatistics};
+    use crate::render::RenderFormat;
+    use teloxide::types::ChatId;
+
+    #[test]
+    fn test_ban_and_statistics() {
+        let mut database = ChatSettingsDatabase::new(RenderFormat::Plain, None);
+
+        database.register_chat(ChatId(1));
+        database.register_chat(ChatId(2));
+        database.toggle_watch(ChatId(2), "P2300");
+        database.set_banned(ChatId(3), true);
+
+        assert!(database.is_banned(ChatId(3)));
+        assert!(!database.is_banned(ChatId(1)));
+
+        let mut chats = database.chats();
+        chats.sort_by_key(|chat_id| chat_id.0);
+        assert_eq!(chats, vec![ChatId(1), ChatId(2)]);
+
+        assert_eq!(
+            database.statistics(),
+            ChatStatistics {
+                chats: 3,
+                banned_chats: 1,
+                watched_papers: 1,
+            }
+        );
+
+        database.set_banned(ChatId(3), false);
+        assert_eq!(database.chats().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_persist_changes() {
+        let path = std::env::temp_dir().join(format!(
+            "npaperbot-chat-settings-{}.json",
+            std::process::id()
+        ));
+        let chat_settings = std::sync::Arc::new(std::sync::Mutex::new(ChatSettingsDatabase::new(
+            RenderFormat::Plain,
+            Some(path.clone()),
+        )));
+
+        chat_settings.lock().unwrap().register_chat(ChatId(1));
+        chat_settings
+            .lock()
+            .unwrap()
+            .set_format(ChatId(2), RenderFormat::Html);
+        persist_changes(&chat_settings).await.unwrap();
+
+        let loaded = ChatSettingsDatabase::load(RenderFormat::Plain, Some(path.clone())).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.statistics().chats, 2);
+        assert_eq!(loaded.format(ChatId(2)), RenderFormat::Html);
+
+        // Nothing has changed, so nothing is written
+        persist_changes(&chat_settings).await.unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -3,34 +3,83 @@ use teloxide::{prelude::*, utils::command::BotCommands};
 #[derive(Clone, BotCommands)]
 #[command(rename = "lowercase", description = "These commands are supported:")]
 pub(crate) enum Command {
+    #[command(
+        description = "show a greeting and the list of commands. \
+        Links like t.me/<bot>?start=P2300R7 open a paper."
+    )]
+    Start(String),
     #[command(description = "display this text.")]
     Help,
     #[command(description = "show generic information about the bot.")]
     About,
-    #[command(description = "search C++ proposal with a title part or an author name.")]
+    #[command(
+        description = "search C++ proposal with a title part or an author name. \
+        Supports since:, until:, year: filters and sort:date."
+    )]
     Search(String),
+    #[command(description = "show detailed information about a paper, e.g. /paper P2300R7.")]
+    Paper(String),
+    #[command(description = "list papers of an author, the latest first.")]
+    Author(String),
+    #[command(description = "list papers of a mailing, e.g. /mailing 2024-10 LEWG.")]
+    Mailing(String),
+    #[command(description = "show or change the output format: markdownv2, html or plain.")]
+    Format(String),
 }
 
-#[allow(unused_assignments)]
+// Every argument is a dependency injected by the dispatcher
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn command_handler(
     msg: Message,
     bot: AutoSend<Bot>,
     command: Command,
     papers: crate::storage::PapersStorage,
+    chat_settings: crate::chat_settings::ChatSettingsStorage,
+    github_issues: crate::github::GithubIssuesStorage,
     limit: u8,
+    me: teloxide::types::Me,
 ) -> anyhow::Result<()> {
     static HELP_TEXT: &str = "Команды:
         (инлайн-режим) - Просто напишите \
         [Nxxxx|Pxxxx|PxxxxRx|Dxxxx|DxxxxRx|CWGxxx|EWGxxx|LWGxxx|LEWGxxx|FSxxx] в любом сообщении
         /about - информация о боте
-        /search - поиск бумаги по её номеру, части названия или автору
+        /search - поиск бумаги по её номеру, части названия или автору. \
+        Поддерживаются фильтры since:2022, until:2023-06, year:2021 и сортировка sort:date
+        /paper - подробная информация о бумаге, например /paper P2300R7. \
+        Кнопки «Поделиться» в карточке и в результатах поиска создают ссылку, которая открывает \
+        карточку бумаги
+        /author - бумаги автора, начиная с последних
+        /mailing - бумаги из рассылки, например /mailing 2024-10 LEWG (по умолчанию - последняя)
+        /format - показать или изменить формат ответов (markdownv2, html, plain)
         /help - показать это сообщение";
+    static GREETING_TEXT: &str = "Привет! Я помогаю найти предложения в стандарт C++ (WG21): \
+        по номеру, названию или автору.";
     static ABOUT_TEXT: &str =
         "Репозиторий бота: https://github.com/ZaMaZaN4iK/npaperbot-telegram .\
         Там вы можете получить более подробную справку, оставить отчёт о проблеме или внести \
         какое-либо предложение.";
 
+    let format = chat_settings.lock().unwrap().format(msg.chat.id);
+
     match command {
+        // Deep links like t.me/<bot>?start=P2300R7 send "/start P2300R7"
+        Command::Start(payload) if !payload.trim().is_empty() => {
+            crate::paper_card::reply_with_paper_card(
+                &bot,
+                &msg,
+                payload.trim(),
+                &papers,
+                &chat_settings,
+                &github_issues,
+                me.username(),
+            )
+            .await?;
+        }
+        Command::Start(_) => {
+            bot.send_message(msg.chat.id, format!("{}\n\n{}", GREETING_TEXT, HELP_TEXT))
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
         Command::Help => {
             bot.send_message(msg.chat.id, HELP_TEXT)
                 .reply_to_message_id(msg.id)
@@ -41,54 +90,99 @@ pub(crate) async fn command_handler(
                 .reply_to_message_id(msg.id)
                 .await?;
         }
-        Command::Search(pattern) => {
-            let mut is_limit_reached = false;
-            let mut found_papers = Vec::<crate::storage::Paper>::new();
-
-            {
-                let paper_database = papers.lock().unwrap();
-                let (is_limit_reached_t, found_papers_t) =
-                    paper_database.search_any(&pattern, limit);
-                is_limit_reached = is_limit_reached_t;
-                found_papers = found_papers_t;
-            }
-
-            if !found_papers.is_empty() {
-                bot.send_message(
-                    msg.chat.id,
-                    crate::utils::convert_papers_to_result(found_papers),
-                )
-                .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-                .reply_to_message_id(msg.id)
-                .await?;
-
-                if is_limit_reached {
+        Command::Search(query) => {
+            let query = match query.parse::<wg21_index::search_query::SearchQuery>() {
+                Ok(query) => query,
+                Err(e) => {
                     bot.send_message(
                         msg.chat.id,
-                        crate::utils::markdown_v2_escape(
-                            format!(
-                                "Показаны только первые {} результатов. \
-                          Если нужного среди них нет - используйте более точный запрос. Спасибо!",
-                                limit
-                            )
-                            .as_str(),
+                        format!(
+                            "Не удалось разобрать запрос: {}. Пример запроса: \
+                            /search coroutines since:2022 until:2023-06 sort:date",
+                            e
                         ),
                     )
-                    .parse_mode(teloxide::types::ParseMode::MarkdownV2)
                     .reply_to_message_id(msg.id)
                     .await?;
+
+                    return Ok(());
                 }
+            };
+
+            let paper_database = papers.snapshot();
+            let (is_limit_reached, found_papers) = {
+                let _timer = crate::metrics::start_search(crate::metrics::SEARCH_KIND_COMMAND);
+                paper_database.search(&query, limit)
+            };
+
+            crate::replies::reply_with_papers(
+                &bot,
+                &msg,
+                &found_papers,
+                is_limit_reached,
+                limit,
+                format.renderer(),
+                me.username(),
+            )
+            .await?;
+        }
+        Command::Paper(number) => {
+            crate::paper_card::reply_with_paper_card(
+                &bot,
+                &msg,
+                &number,
+                &papers,
+                &chat_settings,
+                &github_issues,
+                me.username(),
+            )
+            .await?;
+        }
+        Command::Author(query) => {
+            crate::author::reply_with_author_papers(
+                &bot,
+                &msg,
+                &query,
+                &papers,
+                &chat_settings,
+                limit,
+                me.username(),
+            )
+            .await?;
+        }
+        Command::Mailing(arguments) => {
+            crate::mailing::reply_with_mailing(
+                &bot,
+                &msg,
+                &arguments,
+                &papers,
+                &chat_settings,
+                limit,
+                me.username(),
+            )
+            .await?;
+        }
+        Command::Format(new_format) => {
+            let text = if new_format.trim().is_empty() {
+                format!("Текущий формат ответов: {}", format.name())
             } else {
-                bot.send_message(
-                    msg.chat.id,
-                    crate::utils::markdown_v2_escape(
-                        "К сожалению, по Вашему запросу ничего не найдено. Попробуйте другой запрос!",
-                    )
-                    )
-                    .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-                    .reply_to_message_id(msg.id)
-                    .await?;
-            }
+                match new_format.parse::<crate::render::RenderFormat>() {
+                    Ok(new_format) => {
+                        chat_settings
+                            .lock()
+                            .unwrap()
+                            .set_format(msg.chat.id, new_format);
+                        format!("Формат ответов изменён на {}", new_format.name())
+                    }
+                    Err(_) => {
+                        "Неизвестный формат. Доступные форматы: markdownv2, html, plain".to_string()
+                    }
+                }
+            };
+
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
         }
     };
 
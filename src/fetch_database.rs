@@ -1,40 +1,523 @@
+/// Result of a successful papers database update.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Serialize)]
+pub struct DatabaseUpdateReport {
+    pub papers_count: usize,
+    /// Papers which appeared since the previous update, empty after the first load.
+    pub added: Vec<String>,
+    pub removed: usize,
+    #[serde(serialize_with = "serialize_duration_in_milliseconds")]
+    pub duration: std::time::Duration,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DatabaseUpdateError {
+    /// The index couldn't be downloaded. Such errors are usually transient, so the update is retried.
+    Network(String),
+    /// The server refused to return the index, e.g. `PAPERS_DATABASE_URI` is wrong.
+    Refused(String),
+    /// The index was downloaded but can't be parsed.
+    Malformed(String),
+    /// The index was parsed but looks suspicious, see `ValidationRules`.
+    Rejected(String),
+    UpdaterStopped,
+}
+
+impl DatabaseUpdateError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, DatabaseUpdateError::Network(_))
+    }
+}
+
+impl std::fmt::Display for DatabaseUpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseUpdateError::Network(e) => write!(f, "cannot download the papers index: {}", e),
+            DatabaseUpdateError::Refused(e) => {
+                write!(f, "papers index request is refused: {}", e)
+            }
+            DatabaseUpdateError::Malformed(e) => write!(f, "malformed papers index: {}", e),
+            DatabaseUpdateError::Rejected(e) => write!(f, "papers index is rejected: {}", e),
+            DatabaseUpdateError::UpdaterStopped => write!(f, "papers database updater is stopped"),
+        }
+    }
+}
+
+impl From<wg21_index::fetch::FetchError> for DatabaseUpdateError {
+    fn from(e: wg21_index::fetch::FetchError) -> Self {
+        match e {
+            wg21_index::fetch::FetchError::Network(e) => DatabaseUpdateError::Network(e),
+            wg21_index::fetch::FetchError::Refused(e) => DatabaseUpdateError::Refused(e),
+            wg21_index::fetch::FetchError::Malformed(e) => DatabaseUpdateError::Malformed(e),
+        }
+    }
+}
+
+pub type DatabaseUpdateResult = Result<DatabaseUpdateReport, DatabaseUpdateError>;
+
+/// Exponential backoff for retries of failed updates.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub initial_delay: std::time::Duration,
+    pub max_delay: std::time::Duration,
+    /// After this number of failed retries updates happen with the usual periodicity.
+    pub max_retries: u32,
+}
+
+impl RetryPolicy {
+    /// Delay before the retry with the given number, starting from 1. `jitter` in [0, 1)
+    /// shortens the delay by up to a half, so many instances don't retry at the same moment.
+    pub fn delay(&self, retry: u32, jitter: f64) -> std::time::Duration {
+        let exponent = retry.saturating_sub(1).min(31);
+        let delay = self
+            .initial_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+
+        delay.mul_f64(1.0 - jitter / 2.0)
+    }
+}
+
+/// Checks which protect the live database from an empty or truncated upstream index.
+#[derive(Clone, Copy, Debug)]
+pub struct ValidationRules {
+    pub min_papers: usize,
+    /// The maximum share of papers which may disappear compared to the previous database.
+    pub max_shrink_ratio: f64,
+    /// The maximum share of papers without a title.
+    pub max_untitled_ratio: f64,
+}
+
+impl ValidationRules {
+    /// The same rules, but the database may shrink arbitrarily, e.g. after the upstream
+    /// index intentionally dropped papers.
+    pub fn without_shrink_check(self) -> Self {
+        ValidationRules {
+            max_shrink_ratio: 1.0,
+            ..self
+        }
+    }
+
+    pub fn validate(
+        &self,
+        old: &crate::storage::PaperDatabase,
+        new: &crate::storage::PaperDatabase,
+    ) -> Result<(), DatabaseUpdateError> {
+        let reject = |reason: String| Err(DatabaseUpdateError::Rejected(reason));
+
+        if new.len() < self.min_papers {
+            return reject(format!(
+                "it contains {} papers, at least {} are expected",
+                new.len(),
+                self.min_papers
+            ));
+        }
+
+        if !old.is_empty() {
+            let shrink_ratio = 1.0 - new.len() as f64 / old.len() as f64;
+
+            if shrink_ratio > self.max_shrink_ratio {
+                return reject(format!(
+                    "it contains {} papers instead of {}, at most {:.0}% may disappear",
+                    new.len(),
+                    old.len(),
+                    self.max_shrink_ratio * 100.0
+                ));
+            }
+        }
+
+        let untitled_papers = new.papers().filter(|paper| paper.title.is_none()).count();
+        if untitled_papers as f64 > new.len() as f64 * self.max_untitled_ratio {
+            return reject(format!(
+                "{} of {} papers have no title",
+                untitled_papers,
+                new.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn serialize_duration_in_milliseconds<S: serde::Serializer>(
+    duration: &std::time::Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
+}
+
+/// A request for an immediate database update. The result is sent back to the requester.
+pub struct ReloadRequest {
+    respond_to: tokio::sync::oneshot::Sender<DatabaseUpdateResult>,
+    /// Skip the shrink check of the validation.
+    force: bool,
+}
+
+/// Control channel into `update_database_thread`, used by the /reload admin command,
+/// the reload HTTP endpoint and SIGHUP.
+#[derive(Clone)]
+pub struct DatabaseReloader {
+    requests: tokio::sync::mpsc::Sender<ReloadRequest>,
+}
+
+impl DatabaseReloader {
+    pub fn new() -> (Self, tokio::sync::mpsc::Receiver<ReloadRequest>) {
+        let (requests, receiver) = tokio::sync::mpsc::channel(16);
+        (DatabaseReloader { requests }, receiver)
+    }
+
+    /// Triggers an update and waits for its result.
+    pub async fn reload(&self) -> DatabaseUpdateResult {
+        self.send_request(false).await
+    }
+
+    /// Like `reload`, but the new index may be arbitrarily smaller than the current database.
+    pub async fn force_reload(&self) -> DatabaseUpdateResult {
+        self.send_request(true).await
+    }
+
+    async fn send_request(&self, force: bool) -> DatabaseUpdateResult {
+        let (respond_to, response) = tokio::sync::oneshot::channel();
+
+        self.requests
+            .send(ReloadRequest { respond_to, force })
+            .await
+            .map_err(|_| DatabaseUpdateError::UpdaterStopped)?;
+
+        response
+            .await
+            .map_err(|_| DatabaseUpdateError::UpdaterStopped)?
+    }
+}
+
+// The updater owns its whole configuration
+#[allow(clippy::too_many_arguments)]
 pub async fn update_database_thread(
     papers: crate::storage::PapersStorage,
     uri: url::Url,
+    download_timeout: std::time::Duration,
     update_periodicity: std::time::Duration,
+    new_papers_tx: tokio::sync::mpsc::UnboundedSender<Vec<String>>,
+    mut reload_requests: tokio::sync::mpsc::Receiver<ReloadRequest>,
+    retry_policy: RetryPolicy,
+    validation_rules: ValidationRules,
 ) {
     let mut interval = tokio::time::interval(update_periodicity);
+    let mut failed_retries = 0;
+    let mut retry_delay: Option<std::time::Duration> = None;
 
     loop {
-        interval.tick().await;
+        let mut waiting_requests = Vec::new();
 
-        let new_papers = update_paper_database(uri.clone()).await;
+        tokio::select! {
+            _ = interval.tick(), if retry_delay.is_none() => {}
+            _ = tokio::time::sleep(retry_delay.unwrap_or_default()), if retry_delay.is_some() => {
+                log::info!("Retrying papers database update, attempt {}", failed_retries + 1);
+            }
+            Some(request) = reload_requests.recv() => {
+                log::info!("Forced papers database update");
+                waiting_requests.push(request);
+                // Requests which came before the update starts are served by it as well
+                while let Ok(request) = reload_requests.try_recv() {
+                    waiting_requests.push(request);
+                }
+                interval.reset();
+            }
+        }
 
-        match new_papers {
-            Ok(parsed_papers) => {
-                *papers
-                    .lock()
-                    .expect("An error occurred during papers mutex acquisition") = parsed_papers;
+        // Requests served by the same update can't be validated differently
+        let validation_rules = if waiting_requests.iter().any(|request| request.force) {
+            validation_rules.without_shrink_check()
+        } else {
+            validation_rules
+        };
+        let result = update_database(
+            &papers,
+            &uri,
+            download_timeout,
+            &new_papers_tx,
+            &validation_rules,
+        )
+        .await;
 
-                log::info!(
-                    "Papers database update executed successfully. Papers database size: {}",
-                    papers.lock().unwrap().len()
-                );
+        retry_delay = match &result {
+            Err(e) if e.is_transient() && failed_retries < retry_policy.max_retries => {
+                failed_retries += 1;
+                let delay = retry_policy.delay(failed_retries, rand::random::<f64>());
+                log::warn!("Papers database update will be retried in {:?}", delay);
+                Some(delay)
             }
-            Err(e) => {
-                log::info!("An error occurred during papers database update: {}", e);
+            _ => {
+                if retry_delay.is_some() {
+                    // The usual periodicity resumes from now
+                    interval.reset();
+                }
+                failed_retries = 0;
+                None
             }
+        };
+
+        for request in waiting_requests {
+            // The requester may have stopped waiting
+            let _ = request.respond_to.send(result.clone());
         }
     }
 }
 
-async fn update_paper_database(uri: url::Url) -> reqwest::Result<crate::storage::PaperDatabase> {
-    let resp = reqwest::get(uri)
-        .await?
-        .json::<std::collections::HashMap<String, crate::storage::Paper>>()
-        .await?;
+/// Downloads the index and replaces the database if the index passes `validation_rules`.
+/// Numbers of added papers are sent to `new_papers_tx`. A download which takes longer than
+/// `download_timeout` fails with a network error, so it's retried.
+pub async fn update_database(
+    papers: &crate::storage::PapersStorage,
+    uri: &url::Url,
+    download_timeout: std::time::Duration,
+    new_papers_tx: &tokio::sync::mpsc::UnboundedSender<Vec<String>>,
+    validation_rules: &ValidationRules,
+) -> DatabaseUpdateResult {
+    let started_at = std::time::Instant::now();
+    let timer = crate::metrics::DATABASE_UPDATE_DURATION.start_timer();
+    let new_papers = wg21_index::fetch::fetch_index(uri.clone(), download_timeout).await;
+    timer.observe_duration();
+
+    let result = match new_papers {
+        Ok(parsed_papers) => {
+            // The updater is the only writer, so the database can't change between the checks and the swap
+            let old_papers = papers.snapshot();
+
+            validation_rules
+                .validate(&old_papers, &parsed_papers)
+                .map(|()| {
+                    let (added, removed) = compare_databases(&old_papers, &parsed_papers);
+                    let papers_count = parsed_papers.len();
+
+                    papers.replace(parsed_papers);
+                    crate::metrics::DATABASE_SIZE.set(papers_count as i64);
+
+                    DatabaseUpdateReport {
+                        papers_count,
+                        added,
+                        removed,
+                        duration: started_at.elapsed(),
+                    }
+                })
+        }
+        Err(e) => Err(e.into()),
+    };
 
-    let new_papers = crate::storage::PaperDatabase::new(resp);
+    match &result {
+        Ok(report) => {
+            log::info!(
+                "Papers database update executed successfully. Papers database size: {}",
+                report.papers_count
+            );
+
+            if !report.added.is_empty() {
+                log::info!("New papers: {}", report.added.join(", "));
+
+                if new_papers_tx.send(report.added.clone()).is_err() {
+                    log::warn!("New papers notification receiver is closed");
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("An error occurred during papers database update: {}", e);
+
+            if let DatabaseUpdateError::Rejected(_) = e {
+                crate::metrics::DATABASE_UPDATES_REJECTED.inc();
+            }
+            papers.set_last_update_error(e.to_string());
+        }
+    }
+
+    result
+}
+
+/// Returns sorted numbers of added papers and the number of removed ones.
+fn compare_databases(
+    old: &crate::storage::PaperDatabase,
+    new: &crate::storage::PaperDatabase,
+) -> (Vec<String>, usize) {
+    // Nothing is "new" on the first load, so watchers are not spammed on startup
+    if old.is_empty() {
+        return (Vec::new(), 0);
+    }
+
+    let old_numbers = old.numbers();
+    let new_numbers = new.numbers();
+
+    let mut added: Vec<String> = new_numbers
+        .iter()
+        .filter(|number| !old_numbers.contains(*number))
+        .map(|number| number.to_string())
+        .collect();
+    added.sort_unstable();
+
+    let removed = old_numbers
+        .iter()
+        .filter(|number| !new_numbers.contains(*number))
+        .count();
+
+    (added, removed)
+}
+
+/// Forces a database update on SIGHUP until cancelled.
+#[cfg(unix)]
+pub async fn reload_on_sighup(
+    reloader: DatabaseReloader,
+    cancellation: tokio_util::sync::CancellationToken,
+) {
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        .expect("Cannot listen for SIGHUP");
+
+    loop {
+        let is_stopped = tokio::select! {
+            signal = hangup.recv() => signal.is_none(),
+            _ = cancellation.cancelled() => true,
+        };
 
-    Ok(new_papers)
+        if is_stopped {
+            break;
+        }
+
+        log::info!("SIGHUP received");
+
+        if let Err(e) = reloader.reload().await {
+            log::error!("Forced papers database update failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fetch_database::{
+        compare_databases, DatabaseReloader, DatabaseUpdateError, DatabaseUpdateReport,
+        RetryPolicy, ValidationRules,
+    };
+    use crate::storage::{Paper, PaperDatabase};
+    use std::time::Duration;
+
+    fn database(numbers: &[&str]) -> PaperDatabase {
+        PaperDatabase::new(
+            numbers
+                .iter()
+                .map(|number| (number.to_string(), Paper::default()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_compare_databases() {
+        let old = database(&["P1000R0", "P1001R0", "P1002R0"]);
+        let new = database(&["P1000R0", "P1002R0", "P1002R1", "P1003R0"]);
+
+        assert_eq!(
+            compare_databases(&old, &new),
+            (vec!["P1002R1".to_string(), "P1003R0".to_string()], 1)
+        );
+        assert_eq!(
+            compare_databases(&PaperDatabase::new_empty(), &new),
+            (Vec::new(), 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reloader() {
+        let (reloader, mut requests) = DatabaseReloader::new();
+
+        tokio::spawn(async move {
+            for papers_count in 1..=2 {
+                let request = requests.recv().await.unwrap();
+                assert_eq!(request.force, papers_count == 2);
+                let _ = request.respond_to.send(Ok(DatabaseUpdateReport {
+                    papers_count,
+                    ..Default::default()
+                }));
+            }
+        });
+
+        assert_eq!(reloader.reload().await.unwrap().papers_count, 1);
+        assert_eq!(reloader.force_reload().await.unwrap().papers_count, 2);
+        assert_eq!(
+            reloader.reload().await,
+            Err(DatabaseUpdateError::UpdaterStopped)
+        );
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(300),
+            max_retries: 5,
+        };
+
+        assert_eq!(policy.delay(1, 0.0), Duration::from_secs(30));
+        assert_eq!(policy.delay(2, 0.0), Duration::from_secs(60));
+        assert_eq!(policy.delay(4, 0.0), Duration::from_secs(240));
+        assert_eq!(policy.delay(5, 0.0), Duration::from_secs(300));
+        assert_eq!(policy.delay(100, 0.0), Duration::from_secs(300));
+        assert_eq!(policy.delay(2, 0.5), Duration::from_secs(45));
+
+        assert!(DatabaseUpdateError::Network("timeout".to_string()).is_transient());
+        assert!(!DatabaseUpdateError::Malformed("EOF".to_string()).is_transient());
+        assert!(!DatabaseUpdateError::Refused("404 Not Found".to_string()).is_transient());
+    }
+
+    #[test]
+    fn test_validation_rules() {
+        let rules = ValidationRules {
+            min_papers: 3,
+            max_shrink_ratio: 0.25,
+            max_untitled_ratio: 0.5,
+        };
+        let titled = |numbers: &[&str]| {
+            PaperDatabase::new(
+                numbers
+                    .iter()
+                    .map(|number| {
+                        (
+                            number.to_string(),
+                            Paper {
+                                title: Some("Title".to_string()),
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect(),
+            )
+        };
+
+        let old = titled(&["P1000R0", "P1001R0", "P1002R0", "P1003R0"]);
+        let new = titled(&["P1000R0", "P1001R0", "P1002R0"]);
+
+        assert_eq!(rules.validate(&old, &new), Ok(()));
+        assert_eq!(rules.validate(&PaperDatabase::new_empty(), &new), Ok(()));
+
+        assert!(matches!(
+            rules.validate(&old, &titled(&["P1000R0", "P1001R0"])),
+            Err(DatabaseUpdateError::Rejected(_))
+        ));
+
+        let bigger_old = titled(&["P1", "P2", "P3", "P4", "P5", "P6", "P7", "P8"]);
+        assert!(matches!(
+            rules.validate(&bigger_old, &titled(&["P1", "P2", "P3", "P4", "P5"])),
+            Err(DatabaseUpdateError::Rejected(_))
+        ));
+        assert_eq!(
+            rules
+                .without_shrink_check()
+                .validate(&bigger_old, &titled(&["P1", "P2", "P3"])),
+            Ok(())
+        );
+        assert!(matches!(
+            rules
+                .without_shrink_check()
+                .validate(&bigger_old, &titled(&["P1", "P2"])),
+            Err(DatabaseUpdateError::Rejected(_))
+        ));
+
+        assert!(matches!(
+            rules.validate(&old, &database(&["P1000R0", "P1001R0", "P1002R0"])),
+            Err(DatabaseUpdateError::Rejected(_))
+        ));
+    }
 }
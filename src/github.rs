@@ -0,0 +1,347 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Clone, Deserialize)]
+pub struct GithubLabel {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct GithubMilestone {
+    pub title: String,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct GithubComment {
+    pub created_at: String,
+    #[serde(default)]
+    pub body: String,
+}
+
+/// A tracking issue in the shape returned by the GitHub REST API. The local dump is a
+/// JSON array of these objects, optionally with the issue comments inlined into `comments`.
+#[derive(Clone, Deserialize)]
+pub struct GithubIssue {
+    pub number: u64,
+    pub state: String,
+    #[serde(default)]
+    pub labels: Vec<GithubLabel>,
+    pub milestone: Option<GithubMilestone>,
+    #[serde(default, deserialize_with = "deserialize_inline_comments")]
+    pub comments: Vec<GithubComment>,
+}
+
+// The API returns the number of comments in the `comments` field, the dump may contain the comments themselves
+fn deserialize_inline_comments<'de, D>(deserializer: D) -> Result<Vec<GithubComment>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Comments {
+        Inline(Vec<GithubComment>),
+        Other(serde::de::IgnoredAny),
+    }
+
+    Ok(match Comments::deserialize(deserializer)? {
+        Comments::Inline(comments) => comments,
+        Comments::Other(_) => Vec::new(),
+    })
+}
+
+/// What is shown in a paper card about its tracking issue.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GithubIssueStatus {
+    pub number: u64,
+    pub state: String,
+    pub labels: Vec<String>,
+    pub milestone: Option<String>,
+    /// Date of the latest comment with poll results.
+    pub last_poll: Option<String>,
+}
+
+impl From<GithubIssue> for GithubIssueStatus {
+    fn from(issue: GithubIssue) -> Self {
+        let last_poll = issue
+            .comments
+            .iter()
+            .filter(|comment| comment.body.to_lowercase().contains("poll"))
+            .map(|comment| comment.created_at.clone())
+            .max()
+            .map(|created_at| created_at.chars().take(10).collect());
+
+        GithubIssueStatus {
+            number: issue.number,
+            state: issue.state,
+            labels: issue.labels.into_iter().map(|label| label.name).collect(),
+            milestone: issue.milestone.map(|milestone| milestone.title),
+            last_poll,
+        }
+    }
+}
+
+/// Splits "https://github.com/cplusplus/papers/issues/1054" into ("cplusplus/papers", 1054).
+pub fn parse_issue_url(github_url: &str) -> Option<(String, u64)> {
+    let url = url::Url::parse(github_url).ok()?;
+
+    if url.host_str() != Some("github.com") {
+        return None;
+    }
+
+    let segments: Vec<&str> = url.path_segments()?.collect();
+
+    match segments.as_slice() {
+        [owner, repository, "issues", number, ..] => {
+            Some((format!("{}/{}", owner, repository), number.parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+// A card waits for GitHub, so it shouldn't wait for long
+const GITHUB_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+// The maximum page size of the GitHub API
+const COMMENTS_PER_PAGE: u64 = 100;
+
+struct CachedIssue {
+    fetched_at: std::time::Instant,
+    status: GithubIssueStatus,
+}
+
+enum IssueSource {
+    Api {
+        client: reqwest::Client,
+        cache: std::sync::Mutex<HashMap<(String, u64), CachedIssue>>,
+        cache_ttl: std::time::Duration,
+    },
+    Dump(HashMap<u64, GithubIssueStatus>),
+}
+
+/// Tracking issue states either fetched from the GitHub API or read from a local dump.
+pub struct GithubIssues {
+    source: IssueSource,
+}
+
+impl GithubIssues {
+    pub fn from_api(token: &str, cache_ttl: std::time::Duration) -> anyhow::Result<Self> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse()?,
+        );
+        headers.insert(
+            reqwest::header::ACCEPT,
+            "application/vnd.github+json".parse()?,
+        );
+
+        let client = reqwest::Client::builder()
+            .user_agent("npaperbot-telegram")
+            .default_headers(headers)
+            .timeout(GITHUB_REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(GithubIssues {
+            source: IssueSource::Api {
+                client,
+                cache: std::sync::Mutex::new(HashMap::new()),
+                cache_ttl,
+            },
+        })
+    }
+
+    pub fn from_dump(content: &str) -> anyhow::Result<Self> {
+        let issues: Vec<GithubIssue> = serde_json::from_str(content)?;
+
+        Ok(GithubIssues {
+            source: IssueSource::Dump(
+                issues
+                    .into_iter()
+                    .map(|issue| (issue.number, GithubIssueStatus::from(issue)))
+                    .collect(),
+            ),
+        })
+    }
+
+    pub fn from_dump_file(path: &std::path::Path) -> anyhow::Result<Self> {
+        Self::from_dump(&std::fs::read_to_string(path)?)
+    }
+
+    /// Returns the state of the issue behind `github_url`. Errors are logged and reported as
+    /// the previously fetched state or a missing one, so a card is shown even if GitHub is
+    /// unavailable. Failures aren't cached, the next card tries to fetch the issue again.
+    pub async fn issue_status(&self, github_url: &str) -> Option<GithubIssueStatus> {
+        let (repository, number) = parse_issue_url(github_url)?;
+
+        match &self.source {
+            IssueSource::Dump(issues) => issues.get(&number).cloned(),
+            IssueSource::Api {
+                client,
+                cache,
+                cache_ttl,
+            } => {
+                let key = (repository, number);
+
+                let cached = cache
+                    .lock()
+                    .unwrap()
+                    .get(&key)
+                    .map(|cached| (cached.fetched_at, cached.status.clone()));
+
+                if let Some((fetched_at, status)) = &cached {
+                    if fetched_at.elapsed() < *cache_ttl {
+                        crate::metrics::record_cache_lookup("github_issues", true);
+                        return Some(status.clone());
+                    }
+                }
+
+                crate::metrics::record_cache_lookup("github_issues", false);
+
+                match fetch_issue(client, &key.0, number).await {
+                    Ok(issue) => {
+                        let status = GithubIssueStatus::from(issue);
+                        cache.lock().unwrap().insert(
+                            key,
+                            CachedIssue {
+                                fetched_at: std::time::Instant::now(),
+                                status: status.clone(),
+                            },
+                        );
+
+                        Some(status)
+                    }
+                    Err(e) => {
+                        log::warn!("Cannot fetch GitHub issue {}: {}", github_url, e);
+                        cached.map(|(_, status)| status)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Pages with the newest comments of an issue with `comment_count` comments. The API returns
+/// comments from the oldest one, so these are the last page and the one before it.
+fn newest_comment_pages(comment_count: u64) -> std::ops::RangeInclusive<u64> {
+    let last_page = comment_count.saturating_sub(1) / COMMENTS_PER_PAGE + 1;
+
+    (last_page - 1).max(1)..=last_page
+}
+
+async fn fetch_issue(
+    client: &reqwest::Client,
+    repository: &str,
+    number: u64,
+) -> anyhow::Result<GithubIssue> {
+    let issue_url = format!(
+        "https://api.github.com/repos/{}/issues/{}",
+        repository, number
+    );
+
+    let issue = client
+        .get(&issue_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<serde_json::Value>()
+        .await?;
+
+    let comment_count = issue["comments"].as_u64().unwrap_or(0);
+    let mut issue: GithubIssue = serde_json::from_value(issue)?;
+
+    if comment_count > 0 {
+        for page in newest_comment_pages(comment_count) {
+            let comments = client
+                .get(format!("{}/comments", issue_url))
+                .query(&[("per_page", COMMENTS_PER_PAGE), ("page", page)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<GithubComment>>()
+                .await?;
+
+            issue.comments.extend(comments);
+        }
+    }
+
+    Ok(issue)
+}
+
+pub type GithubIssuesStorage = Option<std::sync::Arc<crate::github::GithubIssues>>;
+
+#[cfg(test)]
+mod tests {
+    use crate::github::{newest_comment_pages, parse_issue_url, GithubIssueStatus, GithubIssues};
+
+    const DUMP: &str = r#"[
+        {
+            "number": 1054,
+            "state": "open",
+            "labels": [{"name": "LEWG"}, {"name": "needs-revision"}],
+            "milestone": {"title": "2023-telecon"},
+            "comments": [
+                {"created_at": "2023-01-10T10:00:00Z", "body": "POLL: Forward P2300 to LWG"},
+                {"created_at": "2023-02-01T10:00:00Z", "body": "Scheduled for Issaquah"},
+                {"created_at": "2022-11-10T10:00:00Z", "body": "Poll results: consensus"}
+            ]
+        },
+        {
+            "number": 1,
+            "state": "closed",
+            "labels": [],
+            "milestone": null,
+            "comments": 3
+        }
+    ]"#;
+
+    #[test]
+    fn test_parse_issue_url() {
+        assert_eq!(
+            parse_issue_url("https://github.com/cplusplus/papers/issues/1054"),
+            Some(("cplusplus/papers".to_string(), 1054))
+        );
+        assert_eq!(parse_issue_url("https://github.com/cplusplus/papers"), None);
+        assert_eq!(parse_issue_url("https://example.com/a/b/issues/1"), None);
+        assert_eq!(parse_issue_url("not a url"), None);
+    }
+
+    #[test]
+    fn test_newest_comment_pages() {
+        assert_eq!(newest_comment_pages(1), 1..=1);
+        assert_eq!(newest_comment_pages(100), 1..=1);
+        assert_eq!(newest_comment_pages(101), 1..=2);
+        assert_eq!(newest_comment_pages(250), 2..=3);
+        assert_eq!(newest_comment_pages(300), 2..=3);
+    }
+
+    #[tokio::test]
+    async fn test_dump() {
+        let issues = GithubIssues::from_dump(DUMP).unwrap();
+
+        assert_eq!(
+            issues
+                .issue_status("https://github.com/cplusplus/papers/issues/1054")
+                .await,
+            Some(GithubIssueStatus {
+                number: 1054,
+                state: "open".to_string(),
+                labels: vec!["LEWG".to_string(), "needs-revision".to_string()],
+                milestone: Some("2023-telecon".to_string()),
+                last_poll: Some("2023-01-10".to_string()),
+            })
+        );
+        assert_eq!(
+            issues
+                .issue_status("https://github.com/cplusplus/papers/issues/1")
+                .await
+                .and_then(|status| status.last_poll),
+            None
+        );
+        assert_eq!(
+            issues
+                .issue_status("https://github.com/cplusplus/papers/issues/2")
+                .await,
+            None
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use teloxide::prelude::*;
+use teloxide::types::{
+    InlineQueryResult, InlineQueryResultArticle, InputMessageContent, InputMessageContentText,
+};
+
+// Telegram doesn't accept more results for a single inline query
+const MAX_INLINE_RESULTS: u8 = 50;
+
+/// Answers "@bot coroutines since:2022" inline queries with matching papers.
+pub(crate) async fn inline_query_handler(
+    query: InlineQuery,
+    bot: AutoSend<Bot>,
+    papers: crate::storage::PapersStorage,
+    chat_settings: crate::chat_settings::ChatSettingsStorage,
+    limit: u8,
+) -> anyhow::Result<()> {
+    let search_query = match query.query.parse::<wg21_index::search_query::SearchQuery>() {
+        Ok(search_query) => search_query,
+        Err(e) => {
+            log::debug!("Inline query is not a valid search query: {}", e);
+            bot.answer_inline_query(query.id, Vec::new()).await?;
+            return Ok(());
+        }
+    };
+
+    // Inline results may be sent to any chat, so the bot-wide format is used
+    let renderer = chat_settings.lock().unwrap().default_format().renderer();
+
+    let paper_database = papers.snapshot();
+    let (_, found_papers) = {
+        let _timer = crate::metrics::start_search(crate::metrics::SEARCH_KIND_INLINE);
+        paper_database.search(&search_query, limit.min(MAX_INLINE_RESULTS))
+    };
+
+    let results = found_papers
+        .iter()
+        .filter_map(|paper| {
+            let number = paper.number.as_ref()?;
+
+            let mut content = InputMessageContentText::new(renderer.render_paper(paper));
+            if let Some(parse_mode) = crate::replies::parse_mode(renderer.format()) {
+                content = content.parse_mode(parse_mode);
+            }
+
+            let mut description = paper.author.clone().unwrap_or_default();
+            if let Some(date) = &paper.date {
+                description.push_str(&format!(" ({})", date));
+            }
+
+            Some(InlineQueryResult::Article(
+                InlineQueryResultArticle::new(
+                    number.clone(),
+                    paper.display_title(),
+                    InputMessageContent::Text(content),
+                )
+                .description(description),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    bot.answer_inline_query(query.id, results).await?;
+
+    Ok(())
+}
@@ -0,0 +1,366 @@
+//! npaperbot-telegram: a Telegram bot which finds C++ proposals in the wg21.link index.
+//! The binary only calls [`run`], the handlers are public so integration tests in `tests/`
+//! can drive the [`dispatcher`] with a fake Bot API.
+
+use crate::fetch_database::update_database_thread;
+use anyhow::anyhow;
+use teloxide::prelude::*;
+use wg21_index::render;
+
+pub mod admin;
+mod author;
+pub mod bot_commands;
+mod callbacks;
+pub mod chat_settings;
+mod commands;
+pub mod fetch_database;
+mod github;
+mod inline_query;
+mod logging;
+mod mailing;
+mod metrics;
+mod paper_card;
+mod parameters;
+mod replies;
+mod service;
+pub mod shutdown;
+pub mod storage;
+mod tls;
+mod watch;
+pub mod webhook;
+
+/// Starts the bot configured with environment variables and runs it until SIGINT or SIGTERM.
+pub async fn run() {
+    logging::init_logger();
+
+    log::info!("Starting npaperbot-telegram");
+
+    let parameters = std::sync::Arc::new(parameters::Parameters::new());
+
+    log::info!("Bot name: {}", parameters.bot_name);
+
+    let bot = Bot::from_env().auto_send();
+
+    let papers = storage::PapersStorage::new(storage::PaperDatabase::new_empty());
+
+    let chat_settings = std::sync::Arc::new(std::sync::Mutex::new(
+        chat_settings::ChatSettingsDatabase::load(
+            parameters.default_output_format,
+            parameters.chat_settings_path.clone(),
+        )
+        .expect("Cannot load chat settings"),
+    ));
+
+    let github_issues: github::GithubIssuesStorage =
+        if let Some(path) = &parameters.github_issues_dump_path {
+            log::info!("GitHub issues are read from {}", path.display());
+            Some(std::sync::Arc::new(
+                github::GithubIssues::from_dump_file(path).expect("Cannot load GitHub issues dump"),
+            ))
+        } else if let Some(token) = &parameters.github_token {
+            log::info!("GitHub issues are fetched from the GitHub API");
+            Some(std::sync::Arc::new(
+                github::GithubIssues::from_api(
+                    token,
+                    parameters
+                        .github_issues_cache_ttl
+                        .to_std()
+                        .expect("Cannot convert Duration to std"),
+                )
+                .expect("Cannot create a GitHub client"),
+            ))
+        } else {
+            None
+        };
+
+    let background_tasks = tokio_util::sync::CancellationToken::new();
+
+    let update_papers = papers.clone();
+    let papers_database_uri = parameters.papers_database_uri.clone();
+    let database_update_periodicity = parameters.database_update_periodicity;
+    let database_download_timeout = parameters
+        .database_download_timeout
+        .to_std()
+        .expect("Cannot convert Duration to std");
+    let (new_papers_tx, new_papers_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (reloader, reload_requests) = fetch_database::DatabaseReloader::new();
+    let retry_policy = fetch_database::RetryPolicy {
+        initial_delay: parameters
+            .database_update_retry_initial_delay
+            .to_std()
+            .expect("Cannot convert Duration to std"),
+        max_delay: database_update_periodicity
+            .to_std()
+            .expect("Cannot convert Duration to std"),
+        max_retries: parameters.database_update_max_retries,
+    };
+    let validation_rules = fetch_database::ValidationRules {
+        min_papers: parameters.database_min_papers,
+        max_shrink_ratio: parameters.database_max_shrink_ratio,
+        max_untitled_ratio: parameters.database_max_untitled_ratio,
+    };
+
+    let persister = tokio::spawn(chat_settings::persist_changes_thread(
+        chat_settings.clone(),
+        background_tasks.clone(),
+    ));
+
+    let updater_cancellation = background_tasks.clone();
+    let updater = tokio::spawn(async move {
+        // The updater doesn't hold locks across await points, so it may be cancelled at any of them
+        tokio::select! {
+            _ = update_database_thread(
+                update_papers,
+                papers_database_uri,
+                database_download_timeout,
+                database_update_periodicity
+                    .to_std()
+                    .expect("Cannot convert Duration to std"),
+                new_papers_tx,
+                reload_requests,
+                retry_policy,
+                validation_rules,
+            ) => {}
+            _ = updater_cancellation.cancelled() => log::info!("Papers database updater is stopped"),
+        }
+    });
+
+    #[cfg(unix)]
+    tokio::spawn(fetch_database::reload_on_sighup(
+        reloader.clone(),
+        background_tasks.clone(),
+    ));
+
+    let service_router = service::service_router(
+        papers.clone(),
+        parameters.readiness_max_database_age,
+        reloader.clone(),
+        parameters.reload_endpoint_token.clone(),
+    );
+
+    // Stops by itself when the updater is stopped and drops its sender
+    let watcher = tokio::spawn(watch::notify_watchers_thread(
+        bot.clone(),
+        new_papers_rx,
+        papers.clone(),
+        chat_settings.clone(),
+    ));
+
+    if !parameters.is_webhook_mode_enabled {
+        log::info!("Webhook deleted");
+        bot.delete_webhook().await.expect("Cannot delete a webhook");
+    }
+
+    let admins = admin::AdminIds::new(parameters.admin_user_ids.clone());
+
+    // The bot works without the menu, so failures are not fatal
+    match bot_commands::register_commands(&bot, &admins).await {
+        Ok(()) => log::info!("Bot commands are registered"),
+        Err(e) => log::error!("Cannot register bot commands: {}", e),
+    }
+
+    let mut bot_dispatcher = dispatcher(
+        bot.clone(),
+        Dependencies {
+            github_issues,
+            ..Dependencies::new(
+                papers,
+                chat_settings.clone(),
+                reloader.clone(),
+                admins,
+                parameters.max_results_per_request,
+            )
+        },
+    );
+
+    tokio::spawn(shutdown::shutdown_on_signal(
+        bot_dispatcher.shutdown_token(),
+    ));
+
+    if parameters.is_webhook_mode_enabled {
+        log::info!("Webhook mode activated");
+        let rx = webhook::webhook(
+            bot.clone(),
+            service_router,
+            webhook::WebhookSettings::from_env(),
+        );
+        bot_dispatcher
+            .dispatch_with_listener(
+                rx.await,
+                LoggingErrorHandler::with_custom_text("An error from the update listener"),
+            )
+            .await;
+    } else {
+        log::info!("Long polling mode activated");
+
+        if parameters.is_service_endpoints_enabled {
+            tokio::spawn(service::serve_service_endpoints(
+                service_router,
+                background_tasks.clone(),
+            ));
+        }
+
+        bot_dispatcher.dispatch().await;
+    }
+
+    log::info!("Dispatcher is stopped");
+
+    // Queued updates are processed, so their changes are flushed by the stopping persister
+    background_tasks.cancel();
+    for task in [updater, watcher, persister] {
+        if let Err(e) = task.await {
+            log::error!("A background task has failed: {}", e);
+        }
+    }
+
+    if parameters.is_webhook_mode_enabled && parameters.delete_webhook_on_shutdown {
+        match bot.delete_webhook().await {
+            Ok(_) => log::info!("Webhook deleted"),
+            Err(e) => log::error!("Cannot delete a webhook: {}", e),
+        }
+    }
+
+    log::info!("npaperbot-telegram is stopped");
+}
+
+/// Everything the handlers need besides the bot.
+pub struct Dependencies {
+    papers: storage::PapersStorage,
+    chat_settings: chat_settings::ChatSettingsStorage,
+    github_issues: github::GithubIssuesStorage,
+    reloader: fetch_database::DatabaseReloader,
+    admins: admin::AdminIds,
+    max_results_per_request: u8,
+}
+
+impl Dependencies {
+    /// Dependencies without the GitHub integration, paper cards don't show issue statuses.
+    pub fn new(
+        papers: storage::PapersStorage,
+        chat_settings: chat_settings::ChatSettingsStorage,
+        reloader: fetch_database::DatabaseReloader,
+        admins: admin::AdminIds,
+        max_results_per_request: u8,
+    ) -> Self {
+        Dependencies {
+            papers,
+            chat_settings,
+            github_issues: None,
+            reloader,
+            admins,
+            max_results_per_request,
+        }
+    }
+}
+
+/// Routes updates to the handlers. Handlers take the [`Dependencies`] passed to [`dispatcher`].
+pub fn handler() -> teloxide::dispatching::UpdateHandler<anyhow::Error> {
+    let message_handler = Update::filter_message()
+        .inspect(
+            |msg: Message, chat_settings: crate::chat_settings::ChatSettingsStorage| {
+                chat_settings.lock().unwrap().register_chat(msg.chat.id);
+            },
+        )
+        .branch(
+            dptree::filter(|msg: Message, admins: admin::AdminIds| admin::is_admin(&msg, &admins))
+                .filter_command::<admin::AdminCommand>()
+                .endpoint(admin::admin_command_handler),
+        )
+        .branch(
+            dptree::entry()
+                .filter_command::<commands::Command>()
+                .endpoint(commands::command_handler),
+        )
+        .branch(
+            dptree::filter(|msg: Message| msg.text().is_some()).endpoint(
+                |msg: Message,
+                 bot: AutoSend<Bot>,
+                 papers: crate::storage::PapersStorage,
+                 chat_settings: crate::chat_settings::ChatSettingsStorage,
+                 max_results_per_request: u8,
+                 me: teloxide::types::Me| async move {
+                    process_message(
+                        msg,
+                        bot,
+                        papers,
+                        chat_settings,
+                        max_results_per_request,
+                        me.username(),
+                    )
+                    .await?;
+                    anyhow::Result::Ok(())
+                },
+            ),
+        );
+
+    dptree::entry()
+        .inspect(|_: Update| metrics::UPDATES_RECEIVED.inc())
+        .filter(
+            |update: Update, chat_settings: crate::chat_settings::ChatSettingsStorage| {
+                !matches!(update.chat(), Some(chat) if chat_settings.lock().unwrap().is_banned(chat.id))
+            },
+        )
+        .branch(message_handler)
+        .branch(Update::filter_callback_query().endpoint(callbacks::callback_handler))
+        .branch(Update::filter_inline_query().endpoint(inline_query::inline_query_handler))
+}
+
+/// Builds the dispatcher with all handlers.
+pub fn dispatcher(
+    bot: AutoSend<Bot>,
+    dependencies: Dependencies,
+) -> Dispatcher<AutoSend<Bot>, anyhow::Error, teloxide::dispatching::DefaultKey> {
+    Dispatcher::builder(bot, handler())
+        .dependencies(dptree::deps![
+            dependencies.papers,
+            dependencies.chat_settings,
+            dependencies.github_issues,
+            dependencies.reloader,
+            dependencies.admins,
+            dependencies.max_results_per_request
+        ])
+        .default_handler(|_| async move {})
+        .error_handler(std::sync::Arc::new(|error: anyhow::Error| async move {
+            metrics::record_handler_error(&error);
+            log::error!("An error has occurred in the dispatcher: {:?}", error);
+        }))
+        .build()
+}
+
+async fn process_message(
+    msg: Message,
+    bot: AutoSend<Bot>,
+    papers: crate::storage::PapersStorage,
+    chat_settings: crate::chat_settings::ChatSettingsStorage,
+    max_results_per_request: u8,
+    bot_username: &str,
+) -> anyhow::Result<()> {
+    let text = msg
+        .text()
+        .ok_or_else(|| anyhow!("Cannot find text in the message"))?;
+
+    // Like the chat registration and the ban filter, it takes the settings lock for every message
+    let format = chat_settings.lock().unwrap().format(msg.chat.id);
+
+    let started_at = std::time::Instant::now();
+    let (is_result_truncated, replies) = match replies::implicit_search_replies(
+        text,
+        &papers.snapshot(),
+        format.renderer(),
+        max_results_per_request,
+        bot_username,
+    ) {
+        Some(search) => search,
+        None => return Ok(()),
+    };
+    metrics::record_search(metrics::SEARCH_KIND_IMPLICIT, started_at.elapsed());
+
+    if is_result_truncated {
+        metrics::RESULTS_TRUNCATED.inc();
+        log::info!("Result is truncated");
+    }
+
+    replies::send_replies(&bot, &msg, replies).await?;
+
+    Ok(())
+}
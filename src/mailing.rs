@@ -0,0 +1,261 @@
+use crate::callbacks::CallbackAction;
+use crate::render::PaperRenderer;
+use crate::storage::PaperDatabase;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+/// A page of papers from a mailing. `mailing` is "YYYY-MM", the latest mailing is
+/// used if it is not specified.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MailingPage {
+    pub mailing: Option<String>,
+    pub subgroup: Option<String>,
+    pub page: usize,
+}
+
+impl MailingPage {
+    /// Parses `/mailing` arguments: an optional mailing and an optional subgroup in any order.
+    pub fn from_command_arguments(arguments: &str) -> Self {
+        let mut page = MailingPage::default();
+
+        for argument in arguments.split_whitespace() {
+            if let Some(mailing) = crate::storage::normalize_mailing_name(argument) {
+                page.mailing = Some(mailing.to_string());
+            } else {
+                // Subgroups are short alphanumeric names, it also keeps callback data small
+                let subgroup: String = argument
+                    .chars()
+                    .filter(char::is_ascii_alphanumeric)
+                    .take(16)
+                    .collect();
+                page.subgroup =
+                    Some(subgroup.to_uppercase()).filter(|subgroup| !subgroup.is_empty());
+            }
+        }
+
+        page
+    }
+
+    /// Renders the page with buttons which share its papers and navigation buttons.
+    /// Returns `None` if there are no papers.
+    pub fn render(
+        &self,
+        paper_database: &PaperDatabase,
+        renderer: &dyn PaperRenderer,
+        page_size: usize,
+        bot_username: &str,
+    ) -> Option<(String, InlineKeyboardMarkup)> {
+        let mailing = match &self.mailing {
+            Some(mailing) => mailing.as_str(),
+            None => paper_database.latest_mailing()?,
+        };
+
+        let papers = paper_database.mailing_papers(mailing, self.subgroup.as_deref());
+        if papers.is_empty() {
+            return None;
+        }
+
+        let page_size = page_size.max(1);
+        let page_count = (papers.len() - 1) / page_size + 1;
+        let page = self.page.min(page_count - 1);
+
+        let mut header = format!("Рассылка {}", mailing);
+        if let Some(subgroup) = &self.subgroup {
+            header.push_str(&format!(" ({})", subgroup));
+        }
+        header.push_str(&format!(
+            ", бумаг: {}, страница {}/{}",
+            papers.len(),
+            page + 1,
+            page_count
+        ));
+
+        let page_papers = &papers[page * page_size..papers.len().min((page + 1) * page_size)];
+
+        let mut lines = vec![renderer.bold(&header)];
+        lines.extend(page_papers.iter().map(|paper| renderer.render_paper(paper)));
+
+        let navigation_button = |text: &str, page: usize| {
+            InlineKeyboardButton::callback(
+                text.to_string(),
+                CallbackAction::Mailing(MailingPage {
+                    mailing: Some(mailing.to_string()),
+                    subgroup: self.subgroup.clone(),
+                    page,
+                })
+                .to_string(),
+            )
+        };
+
+        let mut buttons = Vec::new();
+        if page > 0 {
+            buttons.push(navigation_button("◀", page - 1));
+        }
+        if page + 1 < page_count {
+            buttons.push(navigation_button("▶", page + 1));
+        }
+
+        let mut keyboard =
+            crate::replies::share_keyboard(page_papers, bot_username).unwrap_or_default();
+        if !buttons.is_empty() {
+            keyboard = keyboard.append_row(buttons);
+        }
+
+        Some((lines.join("\n\n"), keyboard))
+    }
+}
+
+impl std::fmt::Display for MailingPage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.mailing.as_deref().unwrap_or_default(),
+            self.subgroup.as_deref().unwrap_or_default(),
+            self.page
+        )
+    }
+}
+
+impl std::str::FromStr for MailingPage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+
+        match parts.as_slice() {
+            [mailing, subgroup, page] => Ok(MailingPage {
+                mailing: crate::storage::normalize_mailing_name(mailing).map(str::to_string),
+                subgroup: Some(subgroup.to_string()).filter(|subgroup| !subgroup.is_empty()),
+                page: page.parse()?,
+            }),
+            _ => Err(anyhow::anyhow!("Invalid mailing page: {}", s)),
+        }
+    }
+}
+
+pub async fn reply_with_mailing(
+    bot: &AutoSend<Bot>,
+    msg: &Message,
+    arguments: &str,
+    papers: &crate::storage::PapersStorage,
+    chat_settings: &crate::chat_settings::ChatSettingsStorage,
+    page_size: u8,
+    bot_username: &str,
+) -> anyhow::Result<()> {
+    let renderer = chat_settings.lock().unwrap().format(msg.chat.id).renderer();
+
+    let timer = crate::metrics::start_search(crate::metrics::SEARCH_KIND_MAILING);
+    let page = MailingPage::from_command_arguments(arguments).render(
+        &papers.snapshot(),
+        renderer,
+        page_size as usize,
+        bot_username,
+    );
+    drop(timer);
+
+    match page {
+        Some((text, keyboard)) => {
+            crate::replies::send_rendered(bot, msg.chat.id, text, renderer)
+                .reply_to_message_id(msg.id)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        None => {
+            crate::replies::reply_rendered(
+                bot,
+                msg,
+                renderer.escape(
+                    "В этой рассылке не найдено ни одной бумаги. Формат запроса: /mailing 2024-10 LEWG",
+                ),
+                renderer,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mailing::MailingPage;
+    use crate::render::RenderFormat;
+    use crate::storage::{Paper, PaperDatabase};
+
+    #[test]
+    fn test_from_command_arguments() {
+        assert_eq!(
+            MailingPage::from_command_arguments("lewg 2024-10"),
+            MailingPage {
+                mailing: Some("2024-10".to_string()),
+                subgroup: Some("LEWG".to_string()),
+                page: 0
+            }
+        );
+        assert_eq!(
+            MailingPage::from_command_arguments("mailing2024-10"),
+            MailingPage {
+                mailing: Some("2024-10".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            MailingPage::from_command_arguments(""),
+            MailingPage::default()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for page in [
+            MailingPage {
+                mailing: Some("2024-10".to_string()),
+                subgroup: Some("LEWG".to_string()),
+                page: 2,
+            },
+            MailingPage::default(),
+        ] {
+            assert_eq!(page.to_string().parse::<MailingPage>().unwrap(), page);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let database = PaperDatabase::new(
+            ["P3000R0", "P3001R0", "P3002R0"]
+                .into_iter()
+                .map(|number| {
+                    (
+                        number.to_string(),
+                        Paper {
+                            date: Some("2024-10-15".to_string()),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+        );
+        let renderer = RenderFormat::Plain.renderer();
+
+        let (text, keyboard) = MailingPage {
+            page: 1,
+            ..Default::default()
+        }
+        .render(&database, renderer, 2, "npaperbot")
+        .unwrap();
+
+        assert_eq!(
+            text,
+            "Рассылка 2024-10, бумаг: 3, страница 2/2\n\nP3002R0 (2024-10-15)"
+        );
+        // Papers of the page are shared, navigation goes last
+        assert_eq!(keyboard.inline_keyboard.len(), 2);
+        assert_eq!(keyboard.inline_keyboard[0][0].text, "Поделиться P3002R0");
+        assert_eq!(keyboard.inline_keyboard[1].len(), 1);
+
+        assert!(MailingPage::from_command_arguments("2024-11")
+            .render(&database, renderer, 2, "npaperbot")
+            .is_none());
+    }
+}
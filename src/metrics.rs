@@ -0,0 +1,137 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Encoder, Histogram, HistogramTimer, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, TextEncoder,
+};
+
+lazy_static! {
+    pub static ref UPDATES_RECEIVED: IntCounter = register_int_counter!(
+        "npaperbot_updates_received_total",
+        "Number of updates received from Telegram"
+    )
+    .expect("Cannot register a metric");
+    pub static ref SEARCHES: IntCounterVec = register_int_counter_vec!(
+        "npaperbot_searches_total",
+        "Number of searches by kind",
+        &["kind"]
+    )
+    .expect("Cannot register a metric");
+    pub static ref SEARCH_DURATION: HistogramVec = register_histogram_vec!(
+        "npaperbot_search_duration_seconds",
+        "Search latency by kind",
+        &["kind"],
+        vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5]
+    )
+    .expect("Cannot register a metric");
+    pub static ref CACHE_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "npaperbot_cache_requests_total",
+        "Number of cache lookups by cache and result (hit or miss)",
+        &["cache", "result"]
+    )
+    .expect("Cannot register a metric");
+    pub static ref RESULTS_TRUNCATED: IntCounter = register_int_counter!(
+        "npaperbot_results_truncated_total",
+        "Number of replies where results were truncated to the per-request limit"
+    )
+    .expect("Cannot register a metric");
+    pub static ref SEND_ERRORS: IntCounter = register_int_counter!(
+        "npaperbot_send_errors_total",
+        "Number of failed requests to the Telegram Bot API"
+    )
+    .expect("Cannot register a metric");
+    pub static ref WEBHOOK_QUEUE_DEPTH: IntGauge = register_int_gauge!(
+        "npaperbot_webhook_queue_depth",
+        "Number of webhook updates waiting for the dispatcher"
+    )
+    .expect("Cannot register a metric");
+    pub static ref WEBHOOK_UPDATES_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "npaperbot_webhook_updates_rejected_total",
+        "Number of webhook updates rejected by reason (full or closed queue)",
+        &["reason"]
+    )
+    .expect("Cannot register a metric");
+    pub static ref DATABASE_SIZE: IntGauge = register_int_gauge!(
+        "npaperbot_papers_database_size",
+        "Number of papers in the database"
+    )
+    .expect("Cannot register a metric");
+    pub static ref DATABASE_UPDATES_REJECTED: IntCounter = register_int_counter!(
+        "npaperbot_database_updates_rejected_total",
+        "Number of papers database updates rejected by validation"
+    )
+    .expect("Cannot register a metric");
+    pub static ref DATABASE_UPDATE_DURATION: Histogram = register_histogram!(
+        "npaperbot_database_update_duration_seconds",
+        "Duration of papers database updates",
+        vec![0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]
+    )
+    .expect("Cannot register a metric");
+}
+
+/// Kinds of searches, used as the `kind` label.
+pub const SEARCH_KIND_IMPLICIT: &str = "implicit";
+pub const SEARCH_KIND_COMMAND: &str = "command";
+pub const SEARCH_KIND_INLINE: &str = "inline";
+pub const SEARCH_KIND_PAPER: &str = "paper";
+pub const SEARCH_KIND_AUTHOR: &str = "author";
+pub const SEARCH_KIND_MAILING: &str = "mailing";
+
+/// Counts a search and measures its latency until the returned timer is dropped.
+pub fn start_search(kind: &str) -> HistogramTimer {
+    SEARCHES.with_label_values(&[kind]).inc();
+    SEARCH_DURATION.with_label_values(&[kind]).start_timer()
+}
+
+/// Counts a search which has already taken `duration`, e.g. when it's unknown in advance
+/// whether a message contains anything to search for.
+pub fn record_search(kind: &str, duration: std::time::Duration) {
+    SEARCHES.with_label_values(&[kind]).inc();
+    SEARCH_DURATION
+        .with_label_values(&[kind])
+        .observe(duration.as_secs_f64());
+}
+
+pub fn record_cache_lookup(cache: &str, is_hit: bool) {
+    let result = if is_hit { "hit" } else { "miss" };
+    CACHE_REQUESTS.with_label_values(&[cache, result]).inc();
+}
+
+/// Counts errors of requests to Telegram among errors returned by handlers.
+pub fn record_handler_error(error: &anyhow::Error) {
+    if error.downcast_ref::<teloxide::RequestError>().is_some() {
+        SEND_ERRORS.inc();
+    }
+}
+
+/// Renders all metrics in the Prometheus text format.
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("Cannot encode metrics");
+
+    String::from_utf8(buffer).expect("Metrics are not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics;
+
+    #[test]
+    fn test_render() {
+        metrics::UPDATES_RECEIVED.inc();
+        drop(metrics::start_search(metrics::SEARCH_KIND_COMMAND));
+        metrics::record_handler_error(&anyhow::Error::from(teloxide::RequestError::RetryAfter(
+            std::time::Duration::from_secs(1),
+        )));
+
+        let rendered = metrics::render();
+
+        assert!(rendered.contains("npaperbot_updates_received_total"));
+        assert!(rendered.contains("npaperbot_searches_total{kind=\"command\"}"));
+        assert!(rendered.contains("npaperbot_search_duration_seconds_bucket{kind=\"command\""));
+        assert!(rendered.contains("npaperbot_send_errors_total"));
+    }
+}
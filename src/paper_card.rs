@@ -0,0 +1,437 @@
+use crate::callbacks::CallbackAction;
+use crate::github::GithubIssueStatus;
+use crate::render::PaperRenderer;
+use crate::storage::Paper;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+/// Rendering of paper cards and revision histories. Cards are shown only by the bot, so it's an
+/// extension of [`PaperRenderer`] available for every renderer.
+pub trait PaperCardRenderer: PaperRenderer {
+    /// Renders all known metadata of a paper, `revisions` are ordered from the oldest one.
+    fn render_paper_card(
+        &self,
+        paper: &Paper,
+        revisions: &[&Paper],
+        issue: Option<&GithubIssueStatus>,
+    ) -> String {
+        let mut lines = vec![self.bold(&paper.display_title())];
+
+        let authors = &paper.authors;
+        if !authors.is_empty() {
+            let label = if authors.len() == 1 {
+                "Автор"
+            } else {
+                "Авторы"
+            };
+            lines.push(self.field(label, &authors.join(", ")));
+        }
+
+        if let Some(subgroup) = &paper.subgroup {
+            lines.push(self.field("Подгруппа", subgroup));
+        }
+
+        if let Some(status) = &paper.status {
+            lines.push(self.field("Статус", status));
+        }
+
+        if let Some(date) = &paper.date {
+            lines.push(self.field("Дата", date));
+        }
+
+        if let Some(github_url) = &paper.github_url {
+            lines.push(format!(
+                "{}{}",
+                self.escape("GitHub: "),
+                self.link("задача", github_url)
+            ));
+        }
+
+        if let Some(issue) = issue {
+            lines.push(self.field("Состояние задачи", issue_state(&issue.state)));
+
+            if !issue.labels.is_empty() {
+                lines.push(self.field("Метки", &issue.labels.join(", ")));
+            }
+
+            if let Some(milestone) = &issue.milestone {
+                lines.push(self.field("Веха", milestone));
+            }
+
+            if let Some(last_poll) = &issue.last_poll {
+                lines.push(self.field("Последнее голосование", last_poll));
+            }
+        }
+
+        if !revisions.is_empty() {
+            let revisions = revisions
+                .iter()
+                .map(|revision| self.revision_link(revision))
+                .collect::<Vec<_>>()
+                .join(&self.escape(", "));
+            lines.push(format!("{}{}", self.escape("Ревизии: "), revisions));
+        }
+
+        let links = document_links(paper)
+            .into_iter()
+            .map(|(label, link)| self.link(label, link))
+            .collect::<Vec<_>>();
+        if !links.is_empty() {
+            lines.push(format!(
+                "{}{}",
+                self.escape("Ссылки: "),
+                links.join(&self.escape(" | "))
+            ));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Renders the revision history of a paper, one revision per line.
+    fn render_history(&self, base_number: &str, revisions: &[&Paper]) -> String {
+        let mut lines = vec![self.bold(&format!("История {}", base_number))];
+
+        for revision in revisions {
+            let mut line = self.revision_link(revision);
+
+            if let Some(date) = &revision.date {
+                line.push_str(&self.escape(&format!(" ({})", date)));
+            }
+
+            if let Some(title) = &revision.title {
+                line.push_str(&self.escape(&format!(": {}", title)));
+            }
+
+            lines.push(line);
+        }
+
+        lines.join("\n")
+    }
+
+    fn field(&self, label: &str, value: &str) -> String {
+        self.escape(&format!("{}: {}", label, value))
+    }
+
+    fn revision_link(&self, revision: &Paper) -> String {
+        let number = revision.number.as_deref().unwrap_or("?");
+
+        match &revision.link {
+            Some(link) => self.link(number, link),
+            None => self.escape(number),
+        }
+    }
+}
+
+impl<R: PaperRenderer + ?Sized> PaperCardRenderer for R {}
+
+// GitHub reports "open" or "closed", other states are shown as is
+fn issue_state(state: &str) -> &str {
+    match state {
+        "open" => "открыта",
+        "closed" => "закрыта",
+        other => other,
+    }
+}
+
+/// Returns labeled links to the documents of a paper: the short wg21.link one and
+/// the full one, labeled by the document type.
+pub fn document_links(paper: &Paper) -> Vec<(&'static str, &str)> {
+    let mut links = Vec::new();
+
+    if let Some(link) = &paper.link {
+        links.push(("wg21.link", link.as_str()));
+    }
+
+    if let Some(long_link) = &paper.long_link {
+        let lowercase_link = long_link.to_lowercase();
+        let label = if lowercase_link.ends_with(".pdf") {
+            "PDF"
+        } else if lowercase_link.ends_with(".html") || lowercase_link.ends_with(".htm") {
+            "HTML"
+        } else {
+            "Документ"
+        };
+
+        links.push((label, long_link.as_str()));
+    }
+
+    links
+}
+
+/// Link which opens the card of the paper in a private chat with the bot, e.g.
+/// `https://t.me/<bot>?start=P2300R7`. `None` if the number can't be a /start payload.
+pub fn paper_deep_link(bot_username: &str, number: &str) -> Option<url::Url> {
+    // Payloads may contain only A-Z, a-z, 0-9, _ and - and be at most 64 characters long
+    let is_valid_payload = !number.is_empty()
+        && number.len() <= 64
+        && number
+            .chars()
+            .all(|chr| chr.is_ascii_alphanumeric() || chr == '_' || chr == '-');
+
+    if !is_valid_payload {
+        return None;
+    }
+
+    let mut link = url::Url::parse("https://t.me/")
+        .ok()?
+        .join(bot_username)
+        .ok()?;
+    link.query_pairs_mut().append_pair("start", number);
+
+    Some(link)
+}
+
+/// Link which opens the Telegram dialog for sharing `link` with `text` to any chat.
+pub fn share_link(link: &url::Url, text: &str) -> url::Url {
+    let mut share_link = url::Url::parse("https://t.me/share/url").expect("Invalid share URL");
+    share_link
+        .query_pairs_mut()
+        .append_pair("url", link.as_str())
+        .append_pair("text", text);
+
+    share_link
+}
+
+/// Button with `text` which shares a deep link to the card of the paper. `None` if the paper
+/// has no number or it can't be a /start payload.
+pub fn share_button(
+    paper: &Paper,
+    bot_username: &str,
+    text: String,
+) -> Option<InlineKeyboardButton> {
+    let deep_link = paper_deep_link(bot_username, paper.number.as_ref()?)?;
+
+    Some(InlineKeyboardButton::url(
+        text,
+        share_link(&deep_link, &paper.display_title()),
+    ))
+}
+
+pub fn card_keyboard(paper: &Paper, bot_username: &str) -> InlineKeyboardMarkup {
+    let mut buttons = Vec::new();
+
+    let link = paper.link.as_ref().or(paper.long_link.as_ref());
+    if let Some(url) = link.and_then(|link| url::Url::parse(link).ok()) {
+        buttons.push(InlineKeyboardButton::url("Открыть".to_string(), url));
+    }
+
+    if let Some(number) = &paper.number {
+        let (base_number, _) = crate::storage::split_revision(number);
+
+        buttons.push(InlineKeyboardButton::callback(
+            "История".to_string(),
+            CallbackAction::History(base_number.to_string()).to_string(),
+        ));
+        buttons.push(InlineKeyboardButton::callback(
+            "Следить".to_string(),
+            CallbackAction::Watch(base_number.to_string()).to_string(),
+        ));
+    }
+
+    if let Some(button) = share_button(paper, bot_username, "Поделиться".to_string()) {
+        buttons.push(button);
+    }
+
+    InlineKeyboardMarkup::new(vec![buttons])
+}
+
+/// Replies with the detailed card of the paper `number` or with a "not found" message.
+pub async fn reply_with_paper_card(
+    bot: &AutoSend<Bot>,
+    msg: &Message,
+    number: &str,
+    papers: &crate::storage::PapersStorage,
+    chat_settings: &crate::chat_settings::ChatSettingsStorage,
+    github_issues: &crate::github::GithubIssuesStorage,
+    bot_username: &str,
+) -> anyhow::Result<()> {
+    let format = chat_settings.lock().unwrap().format(msg.chat.id);
+    let renderer = format.renderer();
+
+    // The issue and the card are taken from the same database, even if it is replaced meanwhile
+    let paper_database = papers.snapshot();
+    let paper = {
+        let _timer = crate::metrics::start_search(crate::metrics::SEARCH_KIND_PAPER);
+        paper_database.resolve(number)
+    };
+
+    let issue = match (
+        github_issues,
+        paper.and_then(|paper| paper.github_url.as_ref()),
+    ) {
+        (Some(github_issues), Some(github_url)) => github_issues.issue_status(github_url).await,
+        _ => None,
+    };
+
+    let card = paper.map(|paper| {
+        let (base_number, _) =
+            crate::storage::split_revision(paper.number.as_deref().unwrap_or(number));
+        let revisions = paper_database.revisions(base_number);
+
+        (
+            renderer.render_paper_card(paper, &revisions, issue.as_ref()),
+            card_keyboard(paper, bot_username),
+        )
+    });
+
+    match card {
+        Some((text, keyboard)) => {
+            crate::replies::send_rendered(bot, msg.chat.id, text, renderer)
+                .reply_to_message_id(msg.id)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        None => {
+            crate::replies::reply_rendered(
+                bot,
+                msg,
+                renderer.escape(
+                    "К сожалению, такая бумага не найдена. Проверьте номер, например P2300R7",
+                ),
+                renderer,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::github::GithubIssueStatus;
+    use crate::paper_card::{card_keyboard, paper_deep_link, share_link, PaperCardRenderer};
+    use crate::render::RenderFormat;
+    use crate::storage::Paper;
+    use teloxide::types::InlineKeyboardButtonKind;
+
+    fn card_paper() -> Paper {
+        Paper {
+            number: Some("P2300R7".to_string()),
+            title: Some("std::execution".to_string()),
+            link: Some("https://wg21.link/p2300r7".to_string()),
+            author: Some("Michał Dominiak, Eric Niebler".to_string()),
+            authors: vec!["Michał Dominiak".to_string(), "Eric Niebler".to_string()],
+            date: Some("2023-04-21".to_string()),
+            github_url: Some("https://github.com/cplusplus/papers/issues/1054".to_string()),
+            long_link: Some(
+                "https://www.open-std.org/jtc1/sc22/wg21/docs/papers/2023/p2300r7.html".to_string(),
+            ),
+            subgroup: Some("LEWG".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn revision(number: &str, date: &str) -> Paper {
+        Paper {
+            number: Some(number.to_string()),
+            link: Some(format!("https://wg21.link/{}", number.to_lowercase())),
+            date: Some(date.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_paper_deep_link() {
+        assert_eq!(
+            paper_deep_link("npaperbot", "P2300R7").unwrap().as_str(),
+            "https://t.me/npaperbot?start=P2300R7"
+        );
+        assert_eq!(paper_deep_link("npaperbot", ""), None);
+        assert_eq!(paper_deep_link("npaperbot", "P2300 R7"), None);
+        assert_eq!(paper_deep_link("npaperbot", &"P".repeat(65)), None);
+    }
+
+    #[test]
+    fn test_share_button() {
+        let paper = Paper {
+            number: Some("P2300R7".to_string()),
+            title: Some("std::execution".to_string()),
+            ..Paper::default()
+        };
+
+        let keyboard = card_keyboard(&paper, "npaperbot");
+        let share_url = keyboard.inline_keyboard[0]
+            .iter()
+            .find_map(|button| match &button.kind {
+                InlineKeyboardButtonKind::Url(url) if button.text == "Поделиться" => {
+                    Some(url.clone())
+                }
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            share_url,
+            share_link(
+                &paper_deep_link("npaperbot", "P2300R7").unwrap(),
+                "P2300R7: std::execution"
+            )
+        );
+        assert_eq!(
+            share_url.as_str(),
+            "https://t.me/share/url?url=https%3A%2F%2Ft.me%2Fnpaperbot%3Fstart%3DP2300R7&text=P2300R7%3A+std%3A%3Aexecution"
+        );
+    }
+
+    #[test]
+    fn test_markdown_v2_card_golden() {
+        let renderer = RenderFormat::MarkdownV2.renderer();
+        let paper = card_paper();
+        let r0 = revision("P2300R0", "2021-06-15");
+
+        assert_eq!(
+            renderer.render_paper_card(&paper, &[&r0, &paper], None),
+            "*P2300R7: std::execution*\n\
+             Авторы: Michał Dominiak, Eric Niebler\n\
+             Подгруппа: LEWG\n\
+             Дата: 2023\\-04\\-21\n\
+             GitHub: [задача](https://github.com/cplusplus/papers/issues/1054)\n\
+             Ревизии: [P2300R0](https://wg21.link/p2300r0), [P2300R7](https://wg21.link/p2300r7)\n\
+             Ссылки: [wg21\\.link](https://wg21.link/p2300r7) \\| \
+             [HTML](https://www.open-std.org/jtc1/sc22/wg21/docs/papers/2023/p2300r7.html)"
+        );
+    }
+
+    #[test]
+    fn test_plain_card_with_issue_golden() {
+        let renderer = RenderFormat::Plain.renderer();
+        let paper = card_paper();
+        let issue = GithubIssueStatus {
+            number: 1054,
+            state: "open".to_string(),
+            labels: vec!["LEWG".to_string(), "needs-revision".to_string()],
+            milestone: Some("2023-telecon".to_string()),
+            last_poll: Some("2023-01-10".to_string()),
+        };
+
+        assert_eq!(
+            renderer.render_paper_card(&paper, &[], Some(&issue)),
+            "P2300R7: std::execution\n\
+             Авторы: Michał Dominiak, Eric Niebler\n\
+             Подгруппа: LEWG\n\
+             Дата: 2023-04-21\n\
+             GitHub: задача <https://github.com/cplusplus/papers/issues/1054>\n\
+             Состояние задачи: открыта\n\
+             Метки: LEWG, needs-revision\n\
+             Веха: 2023-telecon\n\
+             Последнее голосование: 2023-01-10\n\
+             Ссылки: wg21.link <https://wg21.link/p2300r7> | \
+             HTML <https://www.open-std.org/jtc1/sc22/wg21/docs/papers/2023/p2300r7.html>"
+        );
+    }
+
+    #[test]
+    fn test_html_history_golden() {
+        let renderer = RenderFormat::Html.renderer();
+        let r0 = revision("P2300R0", "2021-06-15");
+        let r1 = revision("P2300R1", "2021-07-15");
+
+        assert_eq!(
+            renderer.render_history("P2300", &[&r0, &r1]),
+            "<b>История P2300</b>\n\
+             <a href=\"https://wg21.link/p2300r0\">P2300R0</a> (2021-06-15)\n\
+             <a href=\"https://wg21.link/p2300r1\">P2300R1</a> (2021-07-15)"
+        );
+    }
+}
@@ -6,6 +6,22 @@ pub struct Parameters {
     pub papers_database_uri: url::Url,
     pub max_results_per_request: u8,
     pub database_update_periodicity: chrono::Duration,
+    pub default_output_format: crate::render::RenderFormat,
+    pub chat_settings_path: Option<std::path::PathBuf>,
+    pub github_token: Option<String>,
+    pub github_issues_dump_path: Option<std::path::PathBuf>,
+    pub github_issues_cache_ttl: chrono::Duration,
+    pub is_service_endpoints_enabled: bool,
+    pub readiness_max_database_age: chrono::Duration,
+    pub delete_webhook_on_shutdown: bool,
+    pub admin_user_ids: std::collections::HashSet<teloxide::types::UserId>,
+    pub reload_endpoint_token: Option<String>,
+    pub database_update_retry_initial_delay: chrono::Duration,
+    pub database_update_max_retries: u32,
+    pub database_download_timeout: chrono::Duration,
+    pub database_min_papers: usize,
+    pub database_max_shrink_ratio: f64,
+    pub database_max_untitled_ratio: f64,
 }
 
 impl Parameters {
@@ -21,7 +37,7 @@ impl Parameters {
 
         let papers_database_uri = url::Url::from_str(
             std::env::var("PAPERS_DATABASE_URI")
-                .unwrap_or("https://wg21.link/index.json".to_string())
+                .unwrap_or(wg21_index::fetch::WG21_INDEX_URL.to_string())
                 .as_str(),
         )
         .expect("Cannot parse PAPERS_DATABASE_URI as URI");
@@ -38,12 +54,122 @@ impl Parameters {
                 .expect("Cannot parse DATABASE_UPDATE_PERIODICITY_IN_HOURS as i64"),
         );
 
+        let default_output_format = std::env::var("DEFAULT_OUTPUT_FORMAT")
+            .unwrap_or("markdownv2".to_string())
+            .parse::<crate::render::RenderFormat>()
+            .expect(
+                "Cannot parse DEFAULT_OUTPUT_FORMAT. Applicable values are only \"markdownv2\", \"html\" or \"plain\"",
+            );
+
+        let chat_settings_path = std::env::var("CHAT_SETTINGS_PATH")
+            .ok()
+            .map(std::path::PathBuf::from);
+
+        let github_token = std::env::var("GITHUB_TOKEN").ok();
+
+        let github_issues_dump_path = std::env::var("GITHUB_ISSUES_DUMP_PATH")
+            .ok()
+            .map(std::path::PathBuf::from);
+
+        let github_issues_cache_ttl = chrono::Duration::minutes(
+            std::env::var("GITHUB_ISSUES_CACHE_TTL_IN_MINUTES")
+                .unwrap_or("60".to_string())
+                .parse::<i64>()
+                .expect("Cannot parse GITHUB_ISSUES_CACHE_TTL_IN_MINUTES as i64"),
+        );
+
+        let is_service_endpoints_enabled: bool = std::env::var("SERVICE_ENDPOINTS_ENABLED")
+            .unwrap_or("false".to_string())
+            .parse()
+            .expect(
+                "Cannot convert SERVICE_ENDPOINTS_ENABLED to bool. Applicable values are only \"true\" or \"false\"",
+            );
+
+        let readiness_max_database_age = chrono::Duration::hours(
+            std::env::var("READINESS_MAX_DATABASE_AGE_IN_HOURS")
+                .unwrap_or("24".to_string())
+                .parse::<i64>()
+                .expect("Cannot parse READINESS_MAX_DATABASE_AGE_IN_HOURS as i64"),
+        );
+
+        let delete_webhook_on_shutdown: bool = std::env::var("DELETE_WEBHOOK_ON_SHUTDOWN")
+            .unwrap_or("false".to_string())
+            .parse()
+            .expect(
+                "Cannot convert DELETE_WEBHOOK_ON_SHUTDOWN to bool. Applicable values are only \"true\" or \"false\"",
+            );
+
+        let admin_user_ids = std::env::var("ADMIN_USER_IDS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                teloxide::types::UserId(
+                    id.parse::<u64>()
+                        .expect("Cannot parse ADMIN_USER_IDS as a comma-separated list of u64"),
+                )
+            })
+            .collect();
+
+        let reload_endpoint_token = std::env::var("RELOAD_ENDPOINT_TOKEN").ok();
+
+        let database_update_retry_initial_delay = chrono::Duration::seconds(
+            std::env::var("DATABASE_UPDATE_RETRY_INITIAL_DELAY_IN_SECONDS")
+                .unwrap_or("30".to_string())
+                .parse::<i64>()
+                .expect("Cannot parse DATABASE_UPDATE_RETRY_INITIAL_DELAY_IN_SECONDS as i64"),
+        );
+
+        let database_update_max_retries = std::env::var("DATABASE_UPDATE_MAX_RETRIES")
+            .unwrap_or("5".to_string())
+            .parse::<u32>()
+            .expect("Cannot parse DATABASE_UPDATE_MAX_RETRIES as u32");
+
+        let database_download_timeout = chrono::Duration::seconds(
+            std::env::var("DATABASE_DOWNLOAD_TIMEOUT_IN_SECONDS")
+                .unwrap_or("60".to_string())
+                .parse::<i64>()
+                .expect("Cannot parse DATABASE_DOWNLOAD_TIMEOUT_IN_SECONDS as i64"),
+        );
+
+        let database_min_papers = std::env::var("DATABASE_MIN_PAPERS")
+            .unwrap_or("1000".to_string())
+            .parse::<usize>()
+            .expect("Cannot parse DATABASE_MIN_PAPERS as usize");
+
+        let database_max_shrink_ratio = std::env::var("DATABASE_MAX_SHRINK_RATIO")
+            .unwrap_or("0.1".to_string())
+            .parse::<f64>()
+            .expect("Cannot parse DATABASE_MAX_SHRINK_RATIO as f64");
+
+        let database_max_untitled_ratio = std::env::var("DATABASE_MAX_UNTITLED_RATIO")
+            .unwrap_or("0.5".to_string())
+            .parse::<f64>()
+            .expect("Cannot parse DATABASE_MAX_UNTITLED_RATIO as f64");
+
         Self {
             bot_name,
             is_webhook_mode_enabled,
             papers_database_uri,
             max_results_per_request,
             database_update_periodicity,
+            default_output_format,
+            chat_settings_path,
+            github_token,
+            github_issues_dump_path,
+            github_issues_cache_ttl,
+            is_service_endpoints_enabled,
+            readiness_max_database_age,
+            delete_webhook_on_shutdown,
+            admin_user_ids,
+            reload_endpoint_token,
+            database_update_retry_initial_delay,
+            database_update_max_retries,
+            database_download_timeout,
+            database_min_papers,
+            database_max_shrink_ratio,
+            database_max_untitled_ratio,
         }
     }
 }
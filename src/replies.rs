@@ -0,0 +1,309 @@
+//! Telegram messages with found papers: replies rendered for the parse mode of a chat.
+
+use crate::render::{convert_papers_to_result, PaperRenderer, RenderFormat};
+use crate::storage::{Paper, PaperDatabase};
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardMarkup, ParseMode};
+use wg21_index::implicit_search_request_parser::find_paper_requests;
+
+/// The parse mode Telegram needs to show text rendered in `format`.
+pub fn parse_mode(format: RenderFormat) -> Option<ParseMode> {
+    match format {
+        RenderFormat::MarkdownV2 => Some(ParseMode::MarkdownV2),
+        RenderFormat::Html => Some(ParseMode::Html),
+        RenderFormat::Plain => None,
+    }
+}
+
+/// Prepares a message with `text` using the parse mode of `renderer`.
+pub fn send_rendered(
+    bot: &AutoSend<Bot>,
+    chat_id: ChatId,
+    text: String,
+    renderer: &dyn PaperRenderer,
+) -> <AutoSend<Bot> as Requester>::SendMessage {
+    let request = bot.send_message(chat_id, text);
+
+    match parse_mode(renderer.format()) {
+        Some(parse_mode) => request.parse_mode(parse_mode),
+        None => request,
+    }
+}
+
+/// Sends `text` as a reply to `msg` using the parse mode of `renderer`.
+pub async fn reply_rendered(
+    bot: &AutoSend<Bot>,
+    msg: &Message,
+    text: String,
+    renderer: &dyn PaperRenderer,
+) -> Result<Message, teloxide::RequestError> {
+    send_rendered(bot, msg.chat.id, text, renderer)
+        .reply_to_message_id(msg.id)
+        .await
+}
+
+// Labels with paper numbers are long, so only two of them fit into a row on phones
+const SHARE_BUTTONS_PER_ROW: usize = 2;
+
+/// Buttons which share deep links to the cards of `papers`, see [`crate::paper_card::share_button`].
+/// `None` if none of the papers can be shared.
+pub fn share_keyboard(papers: &[&Paper], bot_username: &str) -> Option<InlineKeyboardMarkup> {
+    let buttons: Vec<_> = papers
+        .iter()
+        .filter_map(|paper| {
+            let text = format!("Поделиться {}", paper.number.as_deref()?);
+            crate::paper_card::share_button(paper, bot_username, text)
+        })
+        .collect();
+
+    if buttons.is_empty() {
+        return None;
+    }
+
+    Some(InlineKeyboardMarkup::new(
+        buttons
+            .chunks(SHARE_BUTTONS_PER_ROW)
+            .map(|row| row.to_vec()),
+    ))
+}
+
+/// A message to send in reply, rendered for `parse_mode`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reply {
+    pub text: String,
+    pub parse_mode: Option<ParseMode>,
+    pub keyboard: Option<InlineKeyboardMarkup>,
+}
+
+impl Reply {
+    fn new(text: String, renderer: &dyn PaperRenderer) -> Self {
+        Reply {
+            text,
+            parse_mode: parse_mode(renderer.format()),
+            keyboard: None,
+        }
+    }
+}
+
+/// Found papers with share buttons and a truncation notice, or a "nothing found" message.
+pub fn papers_replies(
+    papers: &[&Paper],
+    is_result_truncated: bool,
+    limit: u8,
+    renderer: &dyn PaperRenderer,
+    bot_username: &str,
+) -> Vec<Reply> {
+    if papers.is_empty() {
+        return vec![Reply::new(
+            renderer.escape(
+                "К сожалению, по Вашему запросу ничего не найдено. Попробуйте другой запрос!",
+            ),
+            renderer,
+        )];
+    }
+
+    let mut replies = vec![Reply {
+        keyboard: share_keyboard(papers, bot_username),
+        ..Reply::new(convert_papers_to_result(papers, renderer), renderer)
+    }];
+
+    if is_result_truncated {
+        replies.push(Reply::new(
+            renderer.escape(
+                format!(
+                    "Показаны только первые {} результатов. \
+                  Если нужного среди них нет - используйте более точный запрос. Спасибо!",
+                    limit
+                )
+                .as_str(),
+            ),
+            renderer,
+        ));
+    }
+
+    replies
+}
+
+/// Replies to a message with paper requests like "[P2300]" and whether the results are truncated.
+/// `None` if the message has no requests, such messages aren't answered at all.
+pub fn implicit_search_replies(
+    text: &str,
+    paper_database: &PaperDatabase,
+    renderer: &dyn PaperRenderer,
+    limit: u8,
+    bot_username: &str,
+) -> Option<(bool, Vec<Reply>)> {
+    let paper_requests = find_paper_requests(text);
+    if paper_requests.is_empty() {
+        return None;
+    }
+
+    let (is_result_truncated, papers) =
+        paper_database.search_requested_papers(&paper_requests, limit);
+
+    Some((
+        is_result_truncated,
+        papers_replies(&papers, is_result_truncated, limit, renderer, bot_username),
+    ))
+}
+
+/// Sends replies to `msg` one by one.
+pub async fn send_replies(
+    bot: &AutoSend<Bot>,
+    msg: &Message,
+    replies: Vec<Reply>,
+) -> Result<(), teloxide::RequestError> {
+    for reply in replies {
+        let mut request = bot
+            .send_message(msg.chat.id, reply.text)
+            .reply_to_message_id(msg.id);
+
+        if let Some(parse_mode) = reply.parse_mode {
+            request = request.parse_mode(parse_mode);
+        }
+        if let Some(keyboard) = reply.keyboard {
+            request = request.reply_markup(keyboard);
+        }
+
+        request.await?;
+    }
+
+    Ok(())
+}
+
+/// Replies with found papers, a truncation notice or a "nothing found" message.
+pub async fn reply_with_papers(
+    bot: &AutoSend<Bot>,
+    msg: &Message,
+    papers: &[&Paper],
+    is_result_truncated: bool,
+    limit: u8,
+    renderer: &dyn PaperRenderer,
+    bot_username: &str,
+) -> Result<(), teloxide::RequestError> {
+    if is_result_truncated {
+        crate::metrics::RESULTS_TRUNCATED.inc();
+        log::info!("Result is truncated");
+    }
+
+    send_replies(
+        bot,
+        msg,
+        papers_replies(papers, is_result_truncated, limit, renderer, bot_username),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::{MarkdownV2Renderer, PlainTextRenderer};
+    use crate::replies::implicit_search_replies;
+    use crate::storage::{Paper, PaperDatabase};
+    use std::collections::HashMap;
+    use teloxide::types::ParseMode;
+
+    fn database(numbers: &[&str]) -> PaperDatabase {
+        PaperDatabase::new(
+            numbers
+                .iter()
+                .map(|number| {
+                    let paper = Paper {
+                        title: Some(format!("Title of {}", number)),
+                        ..Paper::default()
+                    };
+                    (number.to_string(), paper)
+                })
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn test_implicit_search_replies() {
+        let database = database(&["P2300R0", "P2300R1", "N4861", "P0847R7"]);
+
+        let (is_result_truncated, replies) = implicit_search_replies(
+            "Сравните [P2300] и {n4861}",
+            &database,
+            &PlainTextRenderer,
+            20,
+            "npaperbot",
+        )
+        .unwrap();
+        assert!(!is_result_truncated);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(
+            replies[0].text,
+            "P2300R0: Title of P2300R0\n\nP2300R1: Title of P2300R1\n\nN4861: Title of N4861"
+        );
+        assert_eq!(replies[0].parse_mode, None);
+
+        // Every found paper can be shared
+        let buttons: Vec<Vec<String>> = replies[0]
+            .keyboard
+            .clone()
+            .unwrap()
+            .inline_keyboard
+            .into_iter()
+            .map(|row| row.into_iter().map(|button| button.text).collect())
+            .collect();
+        assert_eq!(
+            buttons,
+            [
+                vec!["Поделиться P2300R0", "Поделиться P2300R1"],
+                vec!["Поделиться N4861"]
+            ]
+        );
+
+        let (_, replies) =
+            implicit_search_replies("[P0847R7]", &database, &MarkdownV2Renderer, 20, "npaperbot")
+                .unwrap();
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].parse_mode, Some(ParseMode::MarkdownV2));
+
+        // Messages without paper requests aren't answered at all
+        assert_eq!(
+            implicit_search_replies("P2300", &database, &PlainTextRenderer, 20, "npaperbot"),
+            None
+        );
+        assert_eq!(
+            implicit_search_replies("", &database, &PlainTextRenderer, 20, "npaperbot"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_implicit_search_replies_truncation() {
+        let database = database(&["P2300R0", "P2300R1", "N4861"]);
+
+        // The limit is shared by all requests of a message
+        let (is_result_truncated, replies) = implicit_search_replies(
+            "[P2300] [N4861]",
+            &database,
+            &PlainTextRenderer,
+            2,
+            "npaperbot",
+        )
+        .unwrap();
+        assert!(is_result_truncated);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(
+            replies[0].text,
+            "P2300R0: Title of P2300R0\n\nP2300R1: Title of P2300R1"
+        );
+        assert!(replies[1]
+            .text
+            .starts_with("Показаны только первые 2 результатов."));
+    }
+
+    #[test]
+    fn test_implicit_search_replies_not_found() {
+        let database = database(&["P2300R0"]);
+
+        let (_, replies) =
+            implicit_search_replies("[P9999]", &database, &PlainTextRenderer, 20, "npaperbot")
+                .unwrap();
+        assert_eq!(replies.len(), 1);
+        assert!(replies[0].text.contains("ничего не найдено"));
+        assert_eq!(replies[0].keyboard, None);
+    }
+}
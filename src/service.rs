@@ -0,0 +1,198 @@
+use axum::response::IntoResponse;
+
+/// State of the papers database as reported by `/readyz`.
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
+pub struct Readiness {
+    pub is_ready: bool,
+    pub papers_count: usize,
+    /// Seconds since the last successful database update, `None` if it was never loaded.
+    pub database_age: Option<i64>,
+    /// Why the latest update failed, e.g. it was rejected by validation. The previous database is served.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_update_error: Option<String>,
+}
+
+/// The bot is ready once the database has been loaded at least once and it isn't older than `max_database_age`.
+pub fn readiness(
+    paper_database: &crate::storage::PaperDatabase,
+    last_update_error: Option<&str>,
+    max_database_age: chrono::Duration,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Readiness {
+    let database_age = paper_database
+        .updated_at()
+        .map(|updated_at| now - updated_at);
+
+    Readiness {
+        is_ready: matches!(database_age, Some(age) if age <= max_database_age),
+        papers_count: paper_database.len(),
+        database_age: database_age.map(|age| age.num_seconds()),
+        last_update_error: last_update_error.map(str::to_string),
+    }
+}
+
+async fn metrics() -> impl IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            prometheus::TEXT_FORMAT.to_string(),
+        )],
+        crate::metrics::render(),
+    )
+}
+
+async fn healthz() -> impl IntoResponse {
+    "OK"
+}
+
+/// The database is not ready if the last successful update is older than this.
+#[derive(Clone, Copy)]
+struct MaxDatabaseAge(chrono::Duration);
+
+async fn readyz(
+    axum::Extension(papers): axum::Extension<crate::storage::PapersStorage>,
+    axum::Extension(MaxDatabaseAge(max_database_age)): axum::Extension<MaxDatabaseAge>,
+) -> impl IntoResponse {
+    let readiness = readiness(
+        &papers.snapshot(),
+        papers.last_update_error().as_deref().map(String::as_str),
+        max_database_age,
+        chrono::Utc::now(),
+    );
+
+    let status = if readiness.is_ready {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, axum::Json(readiness))
+}
+
+/// Token which has to be passed as "Authorization: Bearer <token>" to /reload.
+#[derive(Clone)]
+struct ReloadEndpointToken(String);
+
+async fn reload(
+    headers: axum::http::HeaderMap,
+    axum::Extension(reloader): axum::Extension<crate::fetch_database::DatabaseReloader>,
+    axum::Extension(ReloadEndpointToken(token)): axum::Extension<ReloadEndpointToken>,
+) -> axum::response::Response {
+    let authorization = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+
+    if !crate::webhook::constant_time_eq(authorization, format!("Bearer {}", token).as_bytes()) {
+        return axum::http::StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    match reloader.reload().await {
+        Ok(report) => axum::Json(report).into_response(),
+        Err(error) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": error.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// Routes which are served both in webhook and in long polling mode.
+/// `/reload` is available only if the token for it is configured.
+pub fn service_router(
+    papers: crate::storage::PapersStorage,
+    max_database_age: chrono::Duration,
+    reloader: crate::fetch_database::DatabaseReloader,
+    reload_endpoint_token: Option<String>,
+) -> axum::Router {
+    let mut router = axum::Router::new()
+        .route("/metrics", axum::routing::get(metrics))
+        .route("/healthz", axum::routing::get(healthz))
+        .route("/readyz", axum::routing::get(readyz));
+
+    if let Some(token) = reload_endpoint_token {
+        router = router
+            .route("/reload", axum::routing::post(reload))
+            .layer(axum::Extension(ReloadEndpointToken(token)));
+    }
+
+    router
+        .layer(axum::Extension(papers))
+        .layer(axum::Extension(MaxDatabaseAge(max_database_age)))
+        .layer(axum::Extension(reloader))
+}
+
+pub fn bind_address() -> std::net::SocketAddr {
+    let bind_address = Result::unwrap_or(std::env::var("BIND_ADDRESS"), "0.0.0.0".to_string());
+    let bind_port: u16 = std::env::var("BIND_PORT")
+        .unwrap_or("8080".to_string())
+        .parse()
+        .expect("BIND_PORT value has to be an integer");
+
+    format!("{}:{}", bind_address, bind_port)
+        .parse()
+        .expect("Unable to parse socket address")
+}
+
+/// Serves only the service routes. Used in long polling mode where there is no webhook server.
+pub async fn serve_service_endpoints(
+    router: axum::Router,
+    shutdown: tokio_util::sync::CancellationToken,
+) {
+    let server_address = bind_address();
+
+    log::info!("Service endpoints are available on {}", server_address);
+
+    axum::Server::bind(&server_address)
+        .serve(
+            router
+                .layer(tower_http::trace::TraceLayer::new_for_http())
+                .into_make_service(),
+        )
+        .with_graceful_shutdown(async move { shutdown.cancelled().await })
+        .await
+        .expect("Axum server error")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::{readiness, Readiness};
+    use crate::storage::PaperDatabase;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_readiness() {
+        let now = Utc.ymd(2024, 1, 1).and_hms(12, 0, 0);
+        let max_age = Duration::hours(3);
+
+        let mut database = PaperDatabase::new_empty();
+        assert_eq!(
+            readiness(&database, None, max_age, now),
+            Readiness {
+                is_ready: false,
+                papers_count: 0,
+                database_age: None,
+                last_update_error: None,
+            }
+        );
+
+        database.set_updated_at(now - Duration::hours(1));
+        assert_eq!(
+            readiness(&database, None, max_age, now),
+            Readiness {
+                is_ready: true,
+                papers_count: 0,
+                database_age: Some(3600),
+                last_update_error: None,
+            }
+        );
+
+        database.set_updated_at(now - Duration::hours(4));
+        assert!(!readiness(&database, None, max_age, now).is_ready);
+
+        assert_eq!(
+            readiness(&database, Some("timeout"), max_age, now).last_update_error,
+            Some("timeout".to_string())
+        );
+    }
+}
@@ -0,0 +1,51 @@
+const IDLE_DISPATCHER_SHUTDOWN_RETRY_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(100);
+
+/// Resolves on Ctrl-C or, on Unix, on SIGTERM which is sent by Docker and Kubernetes.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Cannot listen for Ctrl-C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Cannot listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => log::info!("Ctrl-C received"),
+        _ = terminate => log::info!("SIGTERM received"),
+    }
+}
+
+/// Waits for a shutdown signal, then stops the dispatcher.
+/// The dispatcher stops the update listener, which in webhook mode stops accepting requests,
+/// and processes already queued updates before `dispatch` returns.
+pub async fn shutdown_on_signal(dispatcher: teloxide::dispatching::ShutdownToken) {
+    shutdown_signal().await;
+    shutdown(dispatcher).await;
+}
+
+/// Stops the dispatcher. If the dispatcher hasn't started dispatching yet, e.g. the webhook
+/// is still being set up, it's stopped as soon as it starts.
+///
+/// Background tasks keep running, because queued updates may still need them, e.g. changed
+/// settings have to be persisted. They are cancelled once `dispatch` returns.
+pub async fn shutdown(dispatcher: teloxide::dispatching::ShutdownToken) {
+    log::info!("Shutting down");
+
+    loop {
+        match dispatcher.shutdown() {
+            Ok(dispatcher_shutdown) => return dispatcher_shutdown.await,
+            Err(_) => tokio::time::sleep(IDLE_DISPATCHER_SHUTDOWN_RETRY_INTERVAL).await,
+        }
+    }
+}
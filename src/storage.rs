@@ -1,153 +1,66 @@
-use std::ops::AddAssign;
-
-use serde::Deserialize;
-
-use crate::utils;
-use std::collections::HashMap;
-
-#[derive(Clone, Deserialize)]
-pub struct Paper {
-    number: Option<String>,
-    title: Option<String>,
-    link: Option<String>,
-    author: Option<String>,
-    date: Option<String>,
-    github_url: Option<String>,
+pub use wg21_index::storage::*;
+
+/// Shared handle to the current papers database. Readers take immutable snapshots, which are never
+/// blocked by updates, and the updater atomically swaps in a whole new database.
+#[derive(Clone)]
+pub struct PapersStorage {
+    database: std::sync::Arc<arc_swap::ArcSwap<PaperDatabase>>,
+    /// Why the latest update attempt failed, if it did. The database itself stays the previous one.
+    last_update_error: std::sync::Arc<arc_swap::ArcSwapOption<String>>,
 }
 
-impl Paper {
-    pub fn format_with_markdownv2(&self) -> String {
-        let mut new_title = String::new();
-
-        if let Some(number) = &self.number {
-            new_title = number.clone();
-        }
-
-        if let Some(source_title) = &self.title {
-            if !new_title.is_empty() {
-                new_title = format!("{}: {}", new_title, source_title);
-            } else {
-                new_title = source_title.clone();
-            }
-        }
-
-        // In case if number and title are both empty - just fill with some placeholder
-        if new_title.is_empty() {
-            new_title = "Here should be a paper title".to_string();
+impl PapersStorage {
+    pub fn new(database: PaperDatabase) -> Self {
+        PapersStorage {
+            database: std::sync::Arc::new(arc_swap::ArcSwap::from_pointee(database)),
+            last_update_error: std::sync::Arc::new(arc_swap::ArcSwapOption::empty()),
         }
-
-        let mut result: String;
-
-        match &self.link {
-            Some(link) => {
-                result = format!(
-                    "[{}]({})",
-                    utils::markdown_v2_escape(new_title.as_str()),
-                    utils::markdown_v2_escape_inline_uri(link)
-                )
-            }
-            None => result = new_title,
-        }
-
-        if let Some(author) = &self.author {
-            result.add_assign(
-                format!(r#" \(by {}\)"#, utils::markdown_v2_escape(author.as_str())).as_str(),
-            );
-        }
-
-        if let Some(date) = &self.date {
-            result.add_assign(
-                format!(r#" \({}\)"#, utils::markdown_v2_escape(date.as_str())).as_str(),
-            );
-        }
-
-        if let Some(github_url) = &self.github_url {
-            result.add_assign(
-                format!(
-                    r#" \(Related: [GitHub issue]({})\)"#,
-                    utils::markdown_v2_escape(github_url.as_str())
-                )
-                .as_str(),
-            );
-        }
-
-        return result;
     }
-}
 
-pub struct PaperDatabase {
-    database: HashMap<String, Paper>,
-}
-
-impl PaperDatabase {
-    pub fn new_empty() -> Self {
-        PaperDatabase {
-            database: HashMap::<String, Paper>::new(),
-        }
+    /// The current database. It stays valid and unchanged while it's held, even if the database is replaced.
+    pub fn snapshot(&self) -> std::sync::Arc<PaperDatabase> {
+        self.database.load_full()
     }
 
-    pub fn new(mut initial_values: HashMap<String, Paper>) -> Self {
-        for (key, value) in initial_values.iter_mut() {
-            value.number = Option::from(key.clone());
-        }
-
-        PaperDatabase {
-            database: initial_values,
-        }
+    pub fn replace(&self, database: PaperDatabase) {
+        self.database.store(std::sync::Arc::new(database));
+        self.last_update_error.store(None);
     }
 
-    pub fn len(&self) -> usize {
-        return self.database.len();
+    pub fn last_update_error(&self) -> Option<std::sync::Arc<String>> {
+        self.last_update_error.load_full()
     }
 
-    pub fn search_by_number(&self, pattern: &String, limit: u8) -> (bool, Vec<Paper>) {
-        let mut result: Vec<Paper> = Vec::new();
-        let mut is_limit_reached = false;
-
-        let re = regex::RegexBuilder::new(pattern)
-            .case_insensitive(true)
-            .build()
-            .unwrap();
-
-        for (_, paper) in self.database.iter() {
-            if result.len() == limit as usize {
-                is_limit_reached = true;
-                break;
-            }
-
-            if paper.number.is_some() && re.is_match(paper.number.as_ref().unwrap().as_str()) {
-                result.push(paper.clone());
-            }
-        }
-
-        return (is_limit_reached, result);
+    pub fn set_last_update_error(&self, error: String) {
+        self.last_update_error
+            .store(Some(std::sync::Arc::new(error)));
     }
+}
 
-    pub fn search_any(&self, pattern: &String, limit: u8) -> (bool, Vec<Paper>) {
-        let mut result: Vec<Paper> = Vec::new();
-        let mut is_limit_reached = false;
-
-        let re = regex::RegexBuilder::new(pattern)
-            .case_insensitive(true)
-            .build()
-            .unwrap();
-
-        for (_, paper) in self.database.iter() {
-            if result.len() == limit as usize {
-                is_limit_reached = true;
-                break;
-            }
-
-            if (paper.number.is_some() && re.is_match(paper.number.as_ref().unwrap().as_str()))
-                || (paper.title.is_some() && re.is_match(paper.title.as_ref().unwrap().as_str()))
-                || (paper.author.is_some() && re.is_match(paper.author.as_ref().unwrap().as_str()))
-            {
-                result.push(paper.clone());
-            }
-        }
-
-        return (is_limit_reached, result);
+#[cfg(test)]
+mod tests {
+    use crate::storage::{Paper, PaperDatabase, PapersStorage};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_papers_storage_snapshot() {
+        let storage = PapersStorage::new(PaperDatabase::new_empty());
+        let snapshot = storage.snapshot();
+
+        storage.set_last_update_error("timeout".to_string());
+        assert_eq!(
+            storage.last_update_error().as_deref().map(String::as_str),
+            Some("timeout")
+        );
+
+        storage.replace(PaperDatabase::new(HashMap::from([(
+            "P2300R0".to_string(),
+            Paper::default(),
+        )])));
+
+        // The old snapshot isn't affected by the replacement
+        assert!(snapshot.is_empty());
+        assert_eq!(storage.snapshot().len(), 1);
+        assert!(storage.last_update_error().is_none());
     }
 }
-
-pub type PapersStorage = std::sync::Arc<std::sync::Mutex<crate::storage::PaperDatabase>>;
@@ -0,0 +1,72 @@
+/// A certificate and a private key in the PEM format used by the webhook server.
+pub struct TlsCertificate {
+    pub certificate: Vec<u8>,
+    pub private_key: Vec<u8>,
+    /// Self-signed certificates have to be uploaded to Telegram with `setWebhook`.
+    pub is_self_signed: bool,
+}
+
+impl TlsCertificate {
+    pub fn from_files(
+        certificate_path: &std::path::Path,
+        private_key_path: &std::path::Path,
+        is_self_signed: bool,
+    ) -> anyhow::Result<Self> {
+        Ok(TlsCertificate {
+            certificate: std::fs::read(certificate_path)?,
+            private_key: std::fs::read(private_key_path)?,
+            is_self_signed,
+        })
+    }
+
+    /// Generates a certificate for the host, which may contain a port, e.g. "example.com:8443".
+    pub fn self_signed(host: &str) -> anyhow::Result<Self> {
+        let url = url::Url::parse(&format!("https://{}", host))?;
+        let host_name = url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("Cannot find a host name in {}", host))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+
+        let mut params = rcgen::CertificateParams::new(vec![host_name.clone()]);
+        // Telegram checks the common name of self-signed certificates
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, host_name);
+
+        let certificate = rcgen::Certificate::from_params(params)?;
+
+        Ok(TlsCertificate {
+            certificate: certificate.serialize_pem()?.into_bytes(),
+            private_key: certificate.serialize_private_key_pem().into_bytes(),
+            is_self_signed: true,
+        })
+    }
+
+    pub async fn rustls_config(&self) -> std::io::Result<axum_server::tls_rustls::RustlsConfig> {
+        axum_server::tls_rustls::RustlsConfig::from_pem(
+            self.certificate.clone(),
+            self.private_key.clone(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tls::TlsCertificate;
+
+    #[tokio::test]
+    async fn test_self_signed() {
+        let certificate = TlsCertificate::self_signed("bot.example.com:8443").unwrap();
+
+        assert!(certificate.is_self_signed);
+        assert!(String::from_utf8_lossy(&certificate.certificate)
+            .starts_with("-----BEGIN CERTIFICATE-----"));
+        assert!(certificate.rustls_config().await.is_ok());
+
+        assert!(TlsCertificate::self_signed("127.0.0.1").is_ok());
+        assert!(TlsCertificate::self_signed("").is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use teloxide::prelude::*;
+
+/// Notifies chats about new revisions of the papers they watch. Receives numbers of
+/// papers which appeared after a database update.
+pub async fn notify_watchers_thread(
+    bot: AutoSend<Bot>,
+    mut new_papers: tokio::sync::mpsc::UnboundedReceiver<Vec<String>>,
+    papers: crate::storage::PapersStorage,
+    chat_settings: crate::chat_settings::ChatSettingsStorage,
+) {
+    while let Some(numbers) = new_papers.recv().await {
+        for number in numbers {
+            let (base_number, _) = crate::storage::split_revision(&number);
+            let watchers = chat_settings.lock().unwrap().watchers(base_number);
+
+            for chat_id in watchers {
+                let format = chat_settings.lock().unwrap().format(chat_id);
+                let renderer = format.renderer();
+
+                let text = {
+                    let paper_database = papers.snapshot();
+
+                    match paper_database.resolve(&number) {
+                        Some(paper) => format!(
+                            "{}{}",
+                            renderer.escape("Новая ревизия: "),
+                            renderer.render_paper(paper)
+                        ),
+                        None => continue,
+                    }
+                };
+
+                if let Err(e) = crate::replies::send_rendered(&bot, chat_id, text, renderer).await {
+                    crate::metrics::SEND_ERRORS.inc();
+                    log::warn!(
+                        "Cannot notify chat {} about a new revision {}: {}",
+                        chat_id,
+                        number,
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,148 @@
+use axum::body::HttpBody;
 use axum::response::IntoResponse;
 use teloxide::dispatching::{stop_token::AsyncStopToken, update_listeners::StatefulListener};
 use teloxide::prelude::*;
 
+const SECRET_TOKEN_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+type UpdateSender = tokio::sync::mpsc::Sender<Result<teloxide::types::Update, String>>;
+
+/// Networks from which Telegram sends webhook requests, see https://core.telegram.org/bots/webhooks
+const TELEGRAM_NETWORKS: [&str; 2] = ["149.154.160.0/20", "91.108.4.0/22"];
+
+/// Checks which are applied to every incoming webhook request before its body is parsed.
+#[derive(Clone, Debug, Default)]
+pub struct WebhookGuard {
+    /// Expected value of the `X-Telegram-Bot-Api-Secret-Token` header.
+    pub secret_token: Option<String>,
+    /// If empty, requests from any address are accepted.
+    pub allowed_networks: Vec<ipnet::IpNet>,
+    pub max_body_size: usize,
+}
+
+impl WebhookGuard {
+    pub fn check(
+        &self,
+        peer_address: std::net::IpAddr,
+        headers: &axum::http::HeaderMap,
+    ) -> Result<(), axum::http::StatusCode> {
+        if !self.allowed_networks.is_empty()
+            && !self
+                .allowed_networks
+                .iter()
+                .any(|network| network.contains(&peer_address))
+        {
+            log::warn!(
+                "Webhook request from a not allowed address {}",
+                peer_address
+            );
+            return Err(axum::http::StatusCode::FORBIDDEN);
+        }
+
+        if let Some(secret_token) = &self.secret_token {
+            let received_token = headers
+                .get(SECRET_TOKEN_HEADER)
+                .map(|value| value.as_bytes())
+                .unwrap_or_default();
+
+            if !constant_time_eq(received_token, secret_token.as_bytes()) {
+                log::warn!(
+                    "Webhook request from {} with a wrong secret token",
+                    peer_address
+                );
+                return Err(axum::http::StatusCode::UNAUTHORIZED);
+            }
+        }
+
+        let content_length = headers
+            .get(axum::http::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+
+        if matches!(content_length, Some(length) if length > self.max_body_size) {
+            return Err(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+        }
+
+        Ok(())
+    }
+}
+
+/// Telegram accepts secret tokens of 1-256 characters A-Z, a-z, 0-9, _ and -.
+pub fn is_valid_secret_token(secret_token: &str) -> bool {
+    (1..=256).contains(&secret_token.len())
+        && secret_token
+            .chars()
+            .all(|chr| chr.is_ascii_alphanumeric() || chr == '_' || chr == '-')
+}
+
+pub fn constant_time_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len()
+        && lhs
+            .iter()
+            .zip(rhs.iter())
+            .fold(0, |acc, (lhs, rhs)| acc | (lhs ^ rhs))
+            == 0
+}
+
+/// Parses a comma-separated list of networks, "telegram" stands for the Telegram networks.
+pub fn parse_allowed_networks(value: &str) -> anyhow::Result<Vec<ipnet::IpNet>> {
+    let mut networks = Vec::new();
+
+    for network in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        if network.eq_ignore_ascii_case("telegram") {
+            for network in TELEGRAM_NETWORKS {
+                networks.push(network.parse()?);
+            }
+        } else if let Ok(address) = network.parse::<std::net::IpAddr>() {
+            networks.push(ipnet::IpNet::from(address));
+        } else {
+            networks.push(
+                network
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Cannot parse network: {}", network))?,
+            );
+        }
+    }
+
+    Ok(networks)
+}
+
+/// Reads the body, failing as soon as it exceeds the limit, because Content-Length may be absent.
+async fn read_body(
+    mut body: axum::body::Body,
+    max_body_size: usize,
+) -> Result<Vec<u8>, axum::http::StatusCode> {
+    let mut buffer = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
+
+        if buffer.len() + chunk.len() > max_body_size {
+            return Err(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+
+    Ok(buffer)
+}
+
 async fn telegram_request(
-    input: String,
-    tx: axum::extract::Extension<
-        tokio::sync::mpsc::UnboundedSender<Result<teloxide::types::Update, String>>,
-    >,
+    axum::extract::ConnectInfo(peer_address): axum::extract::ConnectInfo<std::net::SocketAddr>,
+    headers: axum::http::HeaderMap,
+    axum::extract::RawBody(body): axum::extract::RawBody,
+    guard: axum::extract::Extension<std::sync::Arc<WebhookGuard>>,
+    tx: axum::extract::Extension<UpdateSender>,
 ) -> impl IntoResponse {
-    let try_parse = match serde_json::from_str(&input) {
+    if let Err(status) = guard.check(peer_address.ip(), &headers) {
+        return status;
+    }
+
+    let input = match read_body(body, guard.max_body_size).await {
+        Ok(input) => input,
+        Err(status) => return status,
+    };
+
+    let try_parse = match serde_json::from_slice(&input) {
         Ok(update) => Ok(update),
         Err(error) => {
             log::error!(
@@ -16,70 +150,341 @@ async fn telegram_request(
                        This is a bug in teloxide, please open an issue here: \
                        https://github.com/teloxide/teloxide/issues.",
                 error,
-                input
+                String::from_utf8_lossy(&input)
             );
             Err(error)
         }
     };
-    if let Ok(update) = try_parse {
-        tx.send(Ok(update))
-            .expect("Cannot send an incoming update from the webhook")
+    match try_parse {
+        Ok(update) => enqueue_update(&tx, update),
+        Err(_) => axum::http::StatusCode::OK,
     }
+}
 
-    axum::http::StatusCode::OK
+/// Doesn't wait for free space in the queue: if it's full, Telegram is asked to retry later.
+fn enqueue_update(tx: &UpdateSender, update: teloxide::types::Update) -> axum::http::StatusCode {
+    match tx.try_send(Ok(update)) {
+        Ok(()) => {
+            crate::metrics::WEBHOOK_QUEUE_DEPTH.inc();
+            axum::http::StatusCode::OK
+        }
+        Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => {
+            log::warn!("Webhook queue is full, the update is rejected");
+            crate::metrics::WEBHOOK_UPDATES_REJECTED
+                .with_label_values(&["full"])
+                .inc();
+            axum::http::StatusCode::TOO_MANY_REQUESTS
+        }
+        Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
+            log::error!("Webhook queue is closed, the update is rejected");
+            crate::metrics::WEBHOOK_UPDATES_REJECTED
+                .with_label_values(&["closed"])
+                .inc();
+            axum::http::StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+/// Built-in TLS is enabled either with certificate files or with a generated self-signed certificate.
+fn tls_certificate(host: &str) -> Option<crate::tls::TlsCertificate> {
+    let is_self_signed: bool = std::env::var("WEBHOOK_TLS_SELF_SIGNED")
+        .unwrap_or("false".to_string())
+        .parse()
+        .expect(
+            "Cannot convert WEBHOOK_TLS_SELF_SIGNED to bool. Applicable values are only \"true\" or \"false\"",
+        );
+
+    match (
+        std::env::var("WEBHOOK_TLS_CERTIFICATE_PATH"),
+        std::env::var("WEBHOOK_TLS_PRIVATE_KEY_PATH"),
+    ) {
+        (Ok(certificate_path), Ok(private_key_path)) => Some(
+            crate::tls::TlsCertificate::from_files(
+                certificate_path.as_ref(),
+                private_key_path.as_ref(),
+                is_self_signed,
+            )
+            .expect("Cannot read the TLS certificate or the private key"),
+        ),
+        (Err(_), Err(_)) if is_self_signed => {
+            log::info!("Generating a self-signed TLS certificate for {}", host);
+            Some(
+                crate::tls::TlsCertificate::self_signed(host)
+                    .expect("Cannot generate a self-signed TLS certificate"),
+            )
+        }
+        (Err(_), Err(_)) => None,
+        _ => panic!(
+            "WEBHOOK_TLS_CERTIFICATE_PATH and WEBHOOK_TLS_PRIVATE_KEY_PATH have to be set together"
+        ),
+    }
+}
+
+/// How the webhook server is set up and where Telegram sends updates to.
+pub struct WebhookSettings {
+    /// The public URL of the webhook which is passed to Telegram.
+    pub url: url::Url,
+    /// The route of the webhook on the server.
+    pub path: String,
+    pub bind_address: std::net::SocketAddr,
+    pub guard: WebhookGuard,
+    /// Number of updates which may wait for the dispatcher.
+    pub queue_capacity: usize,
+    pub tls_certificate: Option<crate::tls::TlsCertificate>,
+}
+
+impl WebhookSettings {
+    pub fn from_env() -> Self {
+        let host = std::env::var("HOST").expect("HOST env variable missing");
+        // The bot token isn't a part of the default path, so it doesn't get into access logs
+        let path = std::env::var("WEBHOOK_PATH").unwrap_or("/api/v1/message".to_string());
+        assert!(path.starts_with('/'), "WEBHOOK_PATH has to start with '/'");
+        let url = format!("https://{}{}", host, path)
+            .parse()
+            .expect("Cannot parse the webhook URL");
+
+        let secret_token = std::env::var("WEBHOOK_SECRET_TOKEN").ok();
+        if let Some(secret_token) = &secret_token {
+            assert!(
+                is_valid_secret_token(secret_token),
+                "WEBHOOK_SECRET_TOKEN has to be 1-256 characters A-Z, a-z, 0-9, _ and -"
+            );
+        }
+
+        let guard = WebhookGuard {
+            secret_token,
+            allowed_networks: parse_allowed_networks(
+                &std::env::var("WEBHOOK_ALLOWED_NETWORKS").unwrap_or_default(),
+            )
+            .expect("Cannot parse WEBHOOK_ALLOWED_NETWORKS"),
+            max_body_size: std::env::var("WEBHOOK_MAX_BODY_SIZE")
+                .unwrap_or("1048576".to_string())
+                .parse()
+                .expect("Cannot parse WEBHOOK_MAX_BODY_SIZE as usize"),
+        };
+
+        let queue_capacity: usize = std::env::var("WEBHOOK_QUEUE_CAPACITY")
+            .unwrap_or("1024".to_string())
+            .parse()
+            .expect("Cannot parse WEBHOOK_QUEUE_CAPACITY as usize");
+
+        WebhookSettings {
+            url,
+            path,
+            bind_address: crate::service::bind_address(),
+            guard,
+            queue_capacity,
+            tls_certificate: tls_certificate(&host),
+        }
+    }
 }
 
 pub async fn webhook(
     bot: AutoSend<Bot>,
+    service_router: axum::Router,
+    settings: WebhookSettings,
 ) -> impl teloxide::dispatching::update_listeners::UpdateListener<String> {
-    let bind_address = Result::unwrap_or(std::env::var("BIND_ADDRESS"), "0.0.0.0".to_string());
-    let bind_port: u16 = std::env::var("BIND_PORT")
-        .unwrap_or("8080".to_string())
-        .parse()
-        .expect("BIND_PORT value has to be an integer");
+    let WebhookSettings {
+        url,
+        path,
+        bind_address: server_address,
+        guard,
+        queue_capacity,
+        tls_certificate,
+    } = settings;
 
-    let teloxide_token =
-        std::env::var("TELOXIDE_TOKEN").expect("TELOXIDE_TOKEN env variable missing");
-    let host = std::env::var("HOST").expect("HOST env variable missing");
-    let path = format!("/{}/api/v1/message", teloxide_token);
-    let url = format!("https://{}{}", host, path);
-
-    bot.set_webhook(url.parse().unwrap())
-        .await
-        .expect("Cannot setup a webhook");
+    let mut set_webhook = bot.set_webhook(url);
+    if let Some(secret_token) = &guard.secret_token {
+        set_webhook = set_webhook.secret_token(secret_token);
+    }
+    if let Some(tls_certificate) = tls_certificate.as_ref().filter(|c| c.is_self_signed) {
+        set_webhook = set_webhook.certificate(
+            teloxide::types::InputFile::memory(tls_certificate.certificate.clone())
+                .file_name("certificate.pem"),
+        );
+    }
+    set_webhook.await.expect("Cannot setup a webhook");
 
-    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let (tx, rx) = tokio::sync::mpsc::channel(queue_capacity);
 
     let app = axum::Router::new()
         .route(path.as_str(), axum::routing::post(telegram_request))
+        .merge(service_router)
         .layer(
             tower::ServiceBuilder::new()
                 .layer(tower_http::trace::TraceLayer::new_for_http())
                 .layer(tower_http::add_extension::AddExtensionLayer::new(tx))
+                .layer(tower_http::add_extension::AddExtensionLayer::new(
+                    std::sync::Arc::new(guard),
+                ))
                 .into_inner(),
         );
 
-    let server_address: std::net::SocketAddr = format!("{}:{}", bind_address, bind_port)
-        .parse()
-        .expect("Unable to parse socket address");
+    let make_service = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
 
-    tokio::spawn(async move {
-        axum::Server::bind(&server_address)
-            .serve(app.into_make_service())
-            .await
-            .expect("Axum server error")
-    });
+    // The dispatcher stops the listener on shutdown: the server stops accepting requests and,
+    // once it's stopped, drops the queue sender, so the dispatcher processes queued updates and stops
+    let (stop_token, stop_flag) = AsyncStopToken::new_pair();
 
-    let stream = tokio_stream::wrappers::UnboundedReceiverStream::new(rx);
+    match tls_certificate {
+        Some(tls_certificate) => {
+            let config = tls_certificate
+                .rustls_config()
+                .await
+                .expect("Cannot load the TLS certificate");
+
+            log::info!("Webhook server uses TLS");
+
+            let handle = axum_server::Handle::new();
+            let shutdown_handle = handle.clone();
+            tokio::spawn(async move {
+                stop_flag.await;
+                shutdown_handle.graceful_shutdown(None);
+            });
+
+            tokio::spawn(async move {
+                axum_server::bind_rustls(server_address, config)
+                    .handle(handle)
+                    .serve(make_service)
+                    .await
+                    .expect("Axum server error")
+            });
+        }
+        None => {
+            tokio::spawn(async move {
+                axum::Server::bind(&server_address)
+                    .serve(make_service)
+                    .with_graceful_shutdown(stop_flag)
+                    .await
+                    .expect("Axum server error")
+            });
+        }
+    }
+
+    let stream =
+        tokio_stream::StreamExt::map(tokio_stream::wrappers::ReceiverStream::new(rx), |update| {
+            crate::metrics::WEBHOOK_QUEUE_DEPTH.dec();
+            update
+        });
 
     fn streamf<S, T>(state: &mut (S, T)) -> &mut S {
         &mut state.0
     }
 
-    let (stop_token, _) = AsyncStopToken::new_pair();
     StatefulListener::new(
         (stream, stop_token),
         streamf,
         |state: &mut (_, AsyncStopToken)| state.1.clone(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::webhook::enqueue_update;
+    use crate::webhook::{is_valid_secret_token, parse_allowed_networks, WebhookGuard};
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+
+    fn guard() -> WebhookGuard {
+        WebhookGuard {
+            secret_token: Some("secret".to_string()),
+            allowed_networks: parse_allowed_networks("telegram, 10.0.0.1").unwrap(),
+            max_body_size: 100,
+        }
+    }
+
+    fn headers(secret_token: &str, content_length: usize) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Telegram-Bot-Api-Secret-Token",
+            HeaderValue::from_str(secret_token).unwrap(),
+        );
+        headers.insert("Content-Length", HeaderValue::from(content_length));
+        headers
+    }
+
+    #[test]
+    fn test_parse_allowed_networks() {
+        assert_eq!(parse_allowed_networks("").unwrap(), Vec::new());
+        assert_eq!(parse_allowed_networks("telegram").unwrap().len(), 2);
+        assert_eq!(
+            parse_allowed_networks("10.0.0.0/8,::1").unwrap(),
+            vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()]
+        );
+        assert!(parse_allowed_networks("10.0.0.0/33").is_err());
+        assert!(parse_allowed_networks("localhost").is_err());
+    }
+
+    #[test]
+    fn test_is_valid_secret_token() {
+        assert!(is_valid_secret_token("secret_Token-1"));
+        assert!(is_valid_secret_token(&"a".repeat(256)));
+
+        assert!(!is_valid_secret_token(""));
+        assert!(!is_valid_secret_token(&"a".repeat(257)));
+        assert!(!is_valid_secret_token("secret token"));
+        assert!(!is_valid_secret_token("секрет"));
+    }
+
+    #[test]
+    fn test_guard() {
+        let guard = guard();
+
+        assert_eq!(
+            guard.check("149.154.167.220".parse().unwrap(), &headers("secret", 10)),
+            Ok(())
+        );
+        assert_eq!(
+            guard.check("10.0.0.1".parse().unwrap(), &headers("secret", 10)),
+            Ok(())
+        );
+        assert_eq!(
+            guard.check("10.0.0.2".parse().unwrap(), &headers("secret", 10)),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            guard.check("91.108.4.1".parse().unwrap(), &headers("secreT", 10)),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            guard.check("91.108.4.1".parse().unwrap(), &HeaderMap::new()),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            guard.check("91.108.4.1".parse().unwrap(), &headers("secret", 101)),
+            Err(StatusCode::PAYLOAD_TOO_LARGE)
+        );
+
+        let open_guard = WebhookGuard {
+            max_body_size: 100,
+            ..Default::default()
+        };
+        assert_eq!(
+            open_guard.check("8.8.8.8".parse().unwrap(), &HeaderMap::new()),
+            Ok(())
+        );
+    }
+
+    fn update() -> teloxide::types::Update {
+        serde_json::from_str(
+            r#"{"update_id": 1, "message": {"message_id": 1, "date": 0, "chat": {"id": 1, "type": "private"}, "text": "[P2300]"}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_enqueue_update() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+
+        assert_eq!(enqueue_update(&tx, update()), StatusCode::OK);
+        assert_eq!(enqueue_update(&tx, update()), StatusCode::TOO_MANY_REQUESTS);
+
+        assert!(rx.try_recv().is_ok());
+        assert_eq!(enqueue_update(&tx, update()), StatusCode::OK);
+
+        drop(rx);
+        assert_eq!(
+            enqueue_update(&tx, update()),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
@@ -0,0 +1,633 @@
+//! End-to-end tests of the dispatcher against a fake Bot API server. The server records every
+//! Bot API request and also serves `tests/fixtures/index.json` as the papers index, so the tests
+//! never touch the network.
+
+use axum::extract::{Extension, Path};
+use npaperbot_telegram::admin::AdminIds;
+use npaperbot_telegram::chat_settings::{ChatSettingsDatabase, ChatSettingsStorage};
+use npaperbot_telegram::fetch_database::{update_database, DatabaseReloader, ValidationRules};
+use npaperbot_telegram::storage::{PaperDatabase, PapersStorage};
+use npaperbot_telegram::webhook::{WebhookGuard, WebhookSettings};
+use npaperbot_telegram::Dependencies;
+use serde_json::{json, Value};
+use teloxide::prelude::*;
+use wg21_index::render::RenderFormat;
+
+use std::sync::{Arc, Mutex};
+
+const BOT_TOKEN: &str = "123456:TEST";
+const ADMIN_ID: u64 = 1;
+const USER_ID: u64 = 2;
+const CHAT_ID: i64 = 100;
+const MAX_RESULTS_PER_REQUEST: u8 = 20;
+
+/// A Bot API request made by the bot.
+#[derive(Clone, Debug)]
+struct BotApiRequest {
+    method: String,
+    body: Value,
+}
+
+type RecordedRequests = Arc<Mutex<Vec<BotApiRequest>>>;
+
+/// Answers Bot API requests like Telegram does and records them.
+struct FakeBotApi {
+    url: url::Url,
+    requests: RecordedRequests,
+}
+
+impl FakeBotApi {
+    async fn start() -> Self {
+        let requests = RecordedRequests::default();
+
+        let router = axum::Router::new()
+            .route(
+                "/index.json",
+                axum::routing::get(|| async { include_str!("fixtures/index.json") }),
+            )
+            .route("/:token/:method", axum::routing::post(bot_api_request))
+            .layer(Extension(requests.clone()));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = url::Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service()),
+        );
+
+        FakeBotApi { url, requests }
+    }
+
+    fn requests(&self, method: &str) -> Vec<Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.method.eq_ignore_ascii_case(method))
+            .map(|request| request.body.clone())
+            .collect()
+    }
+}
+
+async fn bot_api_request(
+    Path((_token, method)): Path<(String, String)>,
+    Extension(requests): Extension<RecordedRequests>,
+    body: axum::body::Bytes,
+) -> axum::Json<Value> {
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+    // Method names are case insensitive, teloxide capitalizes them
+    let result = match method.to_ascii_lowercase().as_str() {
+        "getme" => json!({
+            "id": 42,
+            "is_bot": true,
+            "first_name": "npaperbot",
+            "username": "npaperbot",
+            "can_join_groups": true,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": true
+        }),
+        "sendmessage" => {
+            let message_id = requests.lock().unwrap().len();
+            json!({
+                "message_id": message_id,
+                "date": 0,
+                "chat": {"id": body["chat_id"], "type": "private", "first_name": "Tester"},
+                "text": body["text"]
+            })
+        }
+        _ => json!(true),
+    };
+
+    requests
+        .lock()
+        .unwrap()
+        .push(BotApiRequest { method, body });
+
+    axum::Json(json!({"ok": true, "result": result}))
+}
+
+/// The bot with the fixture papers index, plain text replies and one admin.
+struct TestBot {
+    api: FakeBotApi,
+    papers: PapersStorage,
+    chat_settings: ChatSettingsStorage,
+}
+
+impl TestBot {
+    async fn start() -> Self {
+        let api = FakeBotApi::start().await;
+
+        // The fixture is much smaller than the real index
+        let validation_rules = ValidationRules {
+            min_papers: 1,
+            max_shrink_ratio: 0.1,
+            max_untitled_ratio: 0.5,
+        };
+        let (new_papers_tx, _) = tokio::sync::mpsc::unbounded_channel();
+        let papers = PapersStorage::new(PaperDatabase::new_empty());
+        update_database(
+            &papers,
+            &api.url.join("index.json").unwrap(),
+            wg21_index::fetch::DEFAULT_DOWNLOAD_TIMEOUT,
+            &new_papers_tx,
+            &validation_rules,
+        )
+        .await
+        .expect("Cannot load the fixture index");
+
+        let chat_settings = Arc::new(Mutex::new(ChatSettingsDatabase::new(
+            RenderFormat::Plain,
+            None,
+        )));
+
+        TestBot {
+            api,
+            papers,
+            chat_settings,
+        }
+    }
+
+    fn bot(&self) -> AutoSend<Bot> {
+        Bot::new(BOT_TOKEN)
+            .set_api_url(self.api.url.clone())
+            .auto_send()
+    }
+
+    fn dispatcher(
+        &self,
+    ) -> Dispatcher<AutoSend<Bot>, anyhow::Error, teloxide::dispatching::DefaultKey> {
+        let (reloader, _) = DatabaseReloader::new();
+
+        npaperbot_telegram::dispatcher(
+            self.bot(),
+            Dependencies::new(
+                self.papers.clone(),
+                self.chat_settings.clone(),
+                reloader,
+                AdminIds::new([UserId(ADMIN_ID)].into_iter().collect()),
+                MAX_RESULTS_PER_REQUEST,
+            ),
+        )
+    }
+
+    /// Dispatches the updates and waits until all of them are handled.
+    async fn dispatch(&self, updates: Vec<Update>) {
+        let mut dispatcher = self.dispatcher();
+
+        let updates = futures::stream::iter(
+            updates
+                .into_iter()
+                .map(Ok::<Update, std::convert::Infallible>),
+        );
+
+        dispatcher
+            .dispatch_with_listener(
+                teloxide::dispatching::update_listeners::StatefulListener::from_stream_without_graceful_shutdown(updates),
+                LoggingErrorHandler::new(),
+            )
+            .await;
+    }
+
+    /// Texts of the sent messages in the order they were sent.
+    fn sent_messages(&self) -> Vec<String> {
+        self.api
+            .requests("sendMessage")
+            .iter()
+            .map(|body| body["text"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+}
+
+fn text_message_json(update_id: i32, user_id: u64, text: &str) -> Value {
+    json!({
+        "update_id": update_id,
+        "message": {
+            "message_id": update_id,
+            "date": 0,
+            "chat": {"id": CHAT_ID, "type": "private", "first_name": "Tester"},
+            "from": {"id": user_id, "is_bot": false, "first_name": "Tester"},
+            "text": text
+        }
+    })
+}
+
+fn text_message(update_id: i32, user_id: u64, text: &str) -> Update {
+    // teloxide reads update kinds as borrowed strings, which only JSON text can provide
+    serde_json::from_str(&text_message_json(update_id, user_id, text).to_string()).unwrap()
+}
+
+/// Waits until the bot sends `count` messages, e.g. from a background task.
+async fn wait_for_messages(bot: &TestBot, count: usize) {
+    for _ in 0..50 {
+        if bot.sent_messages().len() >= count {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_implicit_search_request() {
+    let bot = TestBot::start().await;
+
+    bot.dispatch(vec![text_message(
+        1,
+        USER_ID,
+        "Посмотрите [P2300] и {n4861}",
+    )])
+    .await;
+
+    let requests = bot.api.requests("sendMessage");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["chat_id"], CHAT_ID);
+    assert_eq!(requests[0]["reply_to_message_id"], 1);
+
+    let text = requests[0]["text"].as_str().unwrap();
+    let position = |number: &str| text.find(number).unwrap();
+    assert!(position("P2300R0") < position("P2300R7"));
+    assert!(position("P2300R7") < position("N4861"));
+    assert!(!text.contains("P2996"));
+
+    // Found papers can be shared with links which open their cards
+    let share_buttons: Vec<&Value> = requests[0]["reply_markup"]["inline_keyboard"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|row| row.as_array().unwrap())
+        .collect();
+    assert_eq!(share_buttons[0]["text"], "Поделиться P2300R0");
+    assert!(share_buttons.iter().any(|button| button["url"]
+        .as_str()
+        .unwrap()
+        .contains("t.me%2Fnpaperbot%3Fstart%3DN4861")));
+}
+
+#[tokio::test]
+async fn test_message_without_requests_is_ignored() {
+    let bot = TestBot::start().await;
+
+    bot.dispatch(vec![text_message(1, USER_ID, "Кто читал P2300?")])
+        .await;
+
+    assert!(bot.sent_messages().is_empty());
+}
+
+#[tokio::test]
+async fn test_search_command() {
+    let bot = TestBot::start().await;
+
+    bot.dispatch(vec![
+        text_message(1, USER_ID, "/search revzin sort:date"),
+        text_message(2, USER_ID, "/search executors"),
+    ])
+    .await;
+
+    let messages = bot.sent_messages();
+    assert_eq!(messages.len(), 2);
+
+    // Both papers of Barry Revzin, the most recent first
+    assert!(messages[0].starts_with("P2996R0: Reflection for C++26"));
+    assert!(messages[0].contains("P0847R7: Deducing this"));
+
+    assert!(messages[1].contains("ничего не найдено"));
+
+    let requests = bot.api.requests("sendMessage");
+    assert_eq!(
+        requests[0]["reply_markup"]["inline_keyboard"][0][0]["text"],
+        "Поделиться P2996R0"
+    );
+    assert!(requests[1].get("reply_markup").is_none());
+}
+
+#[tokio::test]
+async fn test_help_command() {
+    let bot = TestBot::start().await;
+
+    bot.dispatch(vec![text_message(1, USER_ID, "/help")]).await;
+
+    let messages = bot.sent_messages();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].contains("/search"));
+}
+
+#[tokio::test]
+async fn test_admin_commands() {
+    let bot = TestBot::start().await;
+
+    bot.dispatch(vec![
+        text_message(1, USER_ID, "/stats"),
+        text_message(2, ADMIN_ID, "/stats"),
+    ])
+    .await;
+
+    // Admin commands are unknown to other users
+    let messages = bot.sent_messages();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].contains("Бумаг в базе: 5"));
+}
+
+#[tokio::test]
+async fn test_banned_chat_is_ignored() {
+    let bot = TestBot::start().await;
+    bot.chat_settings
+        .lock()
+        .unwrap()
+        .set_banned(ChatId(CHAT_ID), true);
+
+    bot.dispatch(vec![text_message(1, USER_ID, "[P2300R7]")])
+        .await;
+
+    assert!(bot.sent_messages().is_empty());
+}
+
+#[tokio::test]
+async fn test_start_deep_link() {
+    let bot = TestBot::start().await;
+
+    bot.dispatch(vec![
+        text_message(1, USER_ID, "/start P2300R7"),
+        text_message(2, USER_ID, "/start"),
+    ])
+    .await;
+
+    let requests = bot.api.requests("sendMessage");
+    assert_eq!(requests.len(), 2);
+
+    // The card of the paper with a link which opens the same card
+    assert!(requests[0]["text"].as_str().unwrap().contains("P2300R7"));
+    let share_button = requests[0]["reply_markup"]["inline_keyboard"][0]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|button| button["text"] == "Поделиться")
+        .unwrap();
+    assert!(share_button["url"]
+        .as_str()
+        .unwrap()
+        .contains("t.me%2Fnpaperbot%3Fstart%3DP2300R7"));
+
+    // Without a payload it's a greeting with the list of commands
+    let welcome = requests[1]["text"].as_str().unwrap();
+    assert!(welcome.starts_with("Привет!"));
+    assert!(welcome.contains("/search"));
+}
+
+#[tokio::test]
+async fn test_register_commands() {
+    let api = FakeBotApi::start().await;
+    let bot = Bot::new(BOT_TOKEN).set_api_url(api.url.clone()).auto_send();
+
+    npaperbot_telegram::bot_commands::register_commands(
+        &bot,
+        &AdminIds::new([UserId(ADMIN_ID)].into_iter().collect()),
+    )
+    .await
+    .unwrap();
+
+    let requests = api.requests("setMyCommands");
+    assert_eq!(requests.len(), 8);
+
+    let admin_menu = requests
+        .iter()
+        .find(|body| body["scope"]["type"] == "chat" && body["language_code"] == "ru")
+        .unwrap();
+    assert_eq!(admin_menu["scope"]["chat_id"], ADMIN_ID);
+    assert!(admin_menu["commands"]
+        .as_array()
+        .unwrap()
+        .contains(&json!({"command": "stats", "description": "статистика бота"})));
+}
+
+#[tokio::test]
+async fn test_shutdown_before_dispatching() {
+    use futures::StreamExt;
+    use teloxide::dispatching::stop_token::AsyncStopToken;
+
+    let bot = TestBot::start().await;
+    let mut dispatcher = bot.dispatcher();
+
+    // The signal arrives while the dispatcher is still idle
+    let shutdown = tokio::spawn(npaperbot_telegram::shutdown::shutdown(
+        dispatcher.shutdown_token(),
+    ));
+
+    // Never yields updates, only stops when the dispatcher asks it to
+    type PendingUpdates = futures::stream::TakeUntil<
+        futures::stream::Pending<Result<Update, std::convert::Infallible>>,
+        teloxide::dispatching::stop_token::AsyncStopFlag,
+    >;
+
+    fn updates(state: &mut (PendingUpdates, AsyncStopToken)) -> &mut PendingUpdates {
+        &mut state.0
+    }
+
+    fn stop_token(state: &mut (PendingUpdates, AsyncStopToken)) -> AsyncStopToken {
+        state.1.clone()
+    }
+
+    let (token, flag) = AsyncStopToken::new_pair();
+    let listener = teloxide::dispatching::update_listeners::StatefulListener::new(
+        (futures::stream::pending().take_until(flag), token),
+        updates,
+        stop_token,
+    );
+
+    tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        dispatcher.dispatch_with_listener(listener, LoggingErrorHandler::new()),
+    )
+    .await
+    .expect("The dispatcher isn't stopped");
+
+    shutdown.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_settings_changed_while_draining_are_persisted() {
+    use futures::StreamExt;
+    use teloxide::dispatching::stop_token::AsyncStopToken;
+
+    let path = std::env::temp_dir().join(format!(
+        "npaperbot-drain-settings-{}.json",
+        std::process::id()
+    ));
+    let mut bot = TestBot::start().await;
+    bot.chat_settings = Arc::new(Mutex::new(ChatSettingsDatabase::new(
+        RenderFormat::Plain,
+        Some(path.clone()),
+    )));
+
+    let background_tasks = tokio_util::sync::CancellationToken::new();
+    let persister = tokio::spawn(npaperbot_telegram::chat_settings::persist_changes_thread(
+        bot.chat_settings.clone(),
+        background_tasks.clone(),
+    ));
+
+    let mut dispatcher = bot.dispatcher();
+    let shutdown = tokio::spawn(npaperbot_telegram::shutdown::shutdown(
+        dispatcher.shutdown_token(),
+    ));
+
+    // The update is queued when the listener is stopped, like in the webhook queue on shutdown
+    type QueuedUpdates =
+        futures::stream::BoxStream<'static, Result<Update, std::convert::Infallible>>;
+
+    fn updates(state: &mut (QueuedUpdates, AsyncStopToken)) -> &mut QueuedUpdates {
+        &mut state.0
+    }
+
+    fn stop_token(state: &mut (QueuedUpdates, AsyncStopToken)) -> AsyncStopToken {
+        state.1.clone()
+    }
+
+    let (token, flag) = AsyncStopToken::new_pair();
+    let queued_updates = futures::stream::once(flag)
+        .map(|_| Ok(text_message(1, USER_ID, "/format html")))
+        .boxed();
+    let listener = teloxide::dispatching::update_listeners::StatefulListener::new(
+        (queued_updates, token),
+        updates,
+        stop_token,
+    );
+
+    tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        dispatcher.dispatch_with_listener(listener, LoggingErrorHandler::new()),
+    )
+    .await
+    .expect("The dispatcher isn't stopped");
+    shutdown.await.unwrap();
+
+    // Background tasks are stopped after the dispatcher, like in run()
+    assert!(!persister.is_finished());
+    background_tasks.cancel();
+    persister.await.unwrap();
+
+    let persisted = ChatSettingsDatabase::load(RenderFormat::Plain, Some(path.clone())).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(persisted.format(ChatId(CHAT_ID)), RenderFormat::Html);
+}
+
+#[tokio::test]
+async fn test_broadcast() {
+    let bot = TestBot::start().await;
+
+    bot.dispatch(vec![text_message(
+        1,
+        ADMIN_ID,
+        "/broadcast Новая версия бота",
+    )])
+    .await;
+
+    // The broadcast goes on after the command is handled
+    wait_for_messages(&bot, 3).await;
+
+    let messages = bot.sent_messages();
+    assert_eq!(messages.len(), 3);
+    assert!(messages[0].contains("чатов: 1"));
+    assert_eq!(messages[1], "Новая версия бота");
+    assert!(messages[2].contains("Отправлено сообщений: 1, ошибок: 0"));
+}
+
+/// Posts an update to the webhook, waiting until the server is started.
+async fn post_update(url: &str, secret_token: &str, update: &Value) -> reqwest::StatusCode {
+    let client = reqwest::Client::new();
+
+    for _ in 0..50 {
+        let response = client
+            .post(url)
+            .header("X-Telegram-Bot-Api-Secret-Token", secret_token)
+            .json(update)
+            .send()
+            .await;
+
+        match response {
+            Ok(response) => return response.status(),
+            Err(e) if e.is_connect() => {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await
+            }
+            Err(e) => panic!("Cannot post an update: {}", e),
+        }
+    }
+
+    panic!("The webhook server isn't started");
+}
+
+#[tokio::test]
+async fn test_webhook() {
+    let bot = TestBot::start().await;
+    let mut dispatcher = bot.dispatcher();
+
+    // A free port for the webhook server
+    let bind_address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let listener = npaperbot_telegram::webhook::webhook(
+        bot.bot(),
+        axum::Router::new(),
+        WebhookSettings {
+            url: "https://example.com/webhook".parse().unwrap(),
+            path: "/webhook".to_string(),
+            bind_address,
+            guard: WebhookGuard {
+                secret_token: Some("secret".to_string()),
+                allowed_networks: Vec::new(),
+                max_body_size: 1024 * 1024,
+            },
+            queue_capacity: 16,
+            tls_certificate: None,
+        },
+    )
+    .await;
+
+    let set_webhook = bot.api.requests("setWebhook");
+    assert_eq!(set_webhook.len(), 1);
+    assert_eq!(set_webhook[0]["url"], "https://example.com/webhook");
+    assert_eq!(set_webhook[0]["secret_token"], "secret");
+
+    let shutdown_token = dispatcher.shutdown_token();
+    let dispatching = tokio::spawn(async move {
+        dispatcher
+            .dispatch_with_listener(listener, LoggingErrorHandler::new())
+            .await
+    });
+
+    let webhook_url = format!("http://{}/webhook", bind_address);
+    assert_eq!(
+        post_update(
+            &webhook_url,
+            "wrong",
+            &text_message_json(1, USER_ID, "[P2300R7]")
+        )
+        .await,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        post_update(
+            &webhook_url,
+            "secret",
+            &text_message_json(2, USER_ID, "[P2996]")
+        )
+        .await,
+        reqwest::StatusCode::OK
+    );
+
+    wait_for_messages(&bot, 1).await;
+    let requests = bot.api.requests("sendMessage");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["reply_to_message_id"], 2);
+    assert!(requests[0]["text"].as_str().unwrap().contains("P2996R0"));
+
+    shutdown_token
+        .shutdown()
+        .expect("The dispatcher is idle")
+        .await;
+    tokio::time::timeout(std::time::Duration::from_secs(5), dispatching)
+        .await
+        .expect("The dispatcher isn't stopped")
+        .unwrap();
+}
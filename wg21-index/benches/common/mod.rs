@@ -0,0 +1,54 @@
+//! The index the benchmarks run over: the excerpt of https://wg21.link/index.json in
+//! `data/index.json` with generated papers added up to the size of the full index.
+
+use serde_json::{json, Map, Value};
+
+const EXCERPT: &[u8] = include_bytes!("../data/index.json");
+
+/// The number of entries in the full wg21.link index, including drafts and issues.
+pub const INDEX_SIZE: usize = 30_000;
+
+/// Returns the index as JSON. Numbers of generated papers don't intersect with the excerpt,
+/// so searches for real papers find the same papers as in the full index.
+pub fn full_size_index() -> Vec<u8> {
+    let mut index: Map<String, Value> =
+        serde_json::from_slice(EXCERPT).expect("Invalid index excerpt");
+
+    for i in 0..INDEX_SIZE.saturating_sub(index.len()) {
+        let year = 2000 + i % 24;
+        let subgroup = ["LEWG", "EWG", "SG1", "LWG", "CWG"][i % 5];
+        let topic = [
+            "Coroutines",
+            "Ranges",
+            "Executors",
+            "Contracts",
+            "Pattern matching",
+        ][i % 5];
+        let number = match i % 10 {
+            0 => format!("CWG{}", 5000 + i),
+            1 => format!("LWG{}", 5000 + i),
+            2 => format!("N{}", 6000 + i),
+            _ => format!("P{}R{}", 4000 + i / 3, i % 3),
+        };
+
+        index.insert(
+            number.clone(),
+            json!({
+                "author": format!("Author {}, Author {}", i % 997, i % 89),
+                "date": format!("{}-{:02}-15", year, 1 + i % 12),
+                "link": format!("https://wg21.link/{}", number.to_lowercase()),
+                "long_link": format!(
+                    "https://www.open-std.org/jtc1/sc22/wg21/docs/papers/{}/{}.pdf",
+                    year,
+                    number.to_lowercase()
+                ),
+                "mailing": format!("mailing{}-{:02}", year, 1 + i % 12),
+                "subgroup": subgroup,
+                "title": format!("{} for the standard library, part {}", topic, i),
+                "type": "paper"
+            }),
+        );
+    }
+
+    serde_json::to_vec(&index).expect("Cannot serialize the index")
+}
@@ -0,0 +1,34 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wg21_index::implicit_search_request_parser::find_paper_requests;
+
+// Messages as they are usually written in C++ chats: mostly prose with a few paper references
+const MESSAGES: &[(&str, &str)] = &[
+    ("no_requests", "Кто-нибудь уже пробовал новые модули в GCC 13? У меня что-то не собирается"),
+    ("single_request", "Посмотрите [P2300], там как раз про это"),
+    (
+        "several_requests",
+        "Если кратко: {P0847R7} уже в C++23, <P2996> ещё обсуждают в EWG, а [N4950] - последний черновик",
+    ),
+    (
+        "long_message",
+        "Я долго думал над этим и всё-таки считаю, что без [P1061R5] структурные привязки неполноценны. \
+         С другой стороны, <P2169> решает часть проблем с неиспользуемыми переменными, \
+         а {CWG2518} вообще про другое. Ещё есть [LWG3822] и [EWG24], но это уже мелочи. \
+         В итоге всё упирается в то, что примут в C++26, а это решится только после [P0592R5].",
+    ),
+];
+
+fn bench_find_paper_requests(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_paper_requests");
+
+    for (name, message) in MESSAGES {
+        group.bench_function(*name, |b| {
+            b.iter(|| find_paper_requests(black_box(message)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_find_paper_requests);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wg21_index::render::{
+    convert_papers_to_result, markdown_v2_escape, HtmlRenderer, MarkdownV2Renderer, PaperRenderer,
+    PlainTextRenderer,
+};
+use wg21_index::{PaperDatabase, SearchQuery};
+
+const LIMIT: u8 = 20;
+
+fn bench_markdown_v2_escape(c: &mut Criterion) {
+    let mut group = c.benchmark_group("markdown_v2_escape");
+
+    for (name, text) in [
+        ("plain", "Structured Bindings can introduce a Pack"),
+        (
+            "special characters",
+            "P1967R12: #embed - a simple, scannable preprocessor-based resource acquisition method \
+             (by JeanHeyd Meneide) (2023-11-08)",
+        ),
+        (
+            "long",
+            "constexpr cast from void*: towards constexpr type-erasure (by Corentin Jabot, David Ledger) \
+             (2023-02-13) (Related: [GitHub issue](https://github.com/cplusplus/papers/issues/1408))",
+        ),
+    ] {
+        group.bench_function(name, |b| b.iter(|| markdown_v2_escape(black_box(text))));
+    }
+
+    group.finish();
+}
+
+fn bench_convert_papers_to_result(c: &mut Criterion) {
+    let database = PaperDatabase::from_json(include_bytes!("data/index.json"))
+        .expect("Invalid index snapshot");
+    let query: SearchQuery = "c".parse().expect("Invalid benchmark query");
+    let (_, found_papers) = database.search(&query, LIMIT);
+
+    let renderers: [(&str, &dyn PaperRenderer); 3] = [
+        ("markdown_v2", &MarkdownV2Renderer),
+        ("html", &HtmlRenderer),
+        ("plain_text", &PlainTextRenderer),
+    ];
+
+    let mut group = c.benchmark_group("convert_papers_to_result");
+
+    for (name, renderer) in renderers {
+        group.bench_function(name, |b| {
+            b.iter(|| convert_papers_to_result(black_box(&found_papers), renderer))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_markdown_v2_escape,
+    bench_convert_papers_to_result
+);
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+mod common;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wg21_index::{PaperDatabase, SearchQuery};
+
+const LIMIT: u8 = 20;
+
+fn load_index() -> PaperDatabase {
+    PaperDatabase::from_json(&common::full_size_index()).expect("Invalid index")
+}
+
+fn bench_search_by_number(c: &mut Criterion) {
+    let database = load_index();
+    let mut group = c.benchmark_group("search_by_number");
+
+    for pattern in ["P2300", "P2300R7", "N4", "p0\\d{3}"] {
+        group.bench_function(pattern, |b| {
+            b.iter(|| database.search_by_number(black_box(pattern), LIMIT))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_search(c: &mut Criterion) {
+    let database = load_index();
+    let mut group = c.benchmark_group("search");
+
+    for text in [
+        "ranges",
+        "Barry Revzin",
+        "formatt?ing",
+        "reflection since:2023 sort:date",
+        "year:2022",
+    ] {
+        let query: SearchQuery = text.parse().expect("Invalid benchmark query");
+        group.bench_function(text, |b| {
+            b.iter(|| database.search(black_box(&query), LIMIT))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let database = load_index();
+    let mut group = c.benchmark_group("lookup");
+
+    group.bench_function("exact revision", |b| {
+        b.iter(|| database.resolve(black_box("P2300R7")))
+    });
+    group.bench_function("latest revision", |b| {
+        b.iter(|| database.resolve(black_box("p2300")))
+    });
+    group.bench_function("authors", |b| {
+        b.iter(|| database.find_authors(black_box("Corentin")))
+    });
+
+    group.finish();
+}
+
+fn bench_load_index(c: &mut Criterion) {
+    let index = common::full_size_index();
+
+    c.bench_function("load_index", |b| {
+        b.iter(|| PaperDatabase::from_json(black_box(&index)))
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_search_by_number,
+    bench_search,
+    bench_lookup,
+    bench_load_index
+);
+criterion_main!(benches);
@@ -0,0 +1,145 @@
+//! Compares allocations made by searches returning papers borrowed from the database with the
+//! previous search which cloned every found paper, over the full-size index of the other benchmarks.
+//! Run with `cargo bench -p wg21-index --bench search_allocations`.
+
+mod common;
+
+use wg21_index::search_query::{SearchQuery, SortOrder};
+use wg21_index::storage::{Paper, PaperDatabase};
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: u32 = 100;
+const LIMIT: u8 = 20;
+
+struct Measurement {
+    allocations: usize,
+    bytes: usize,
+    duration: Duration,
+}
+
+/// The search as it was before results were borrowed: papers are cloned out of the database
+/// while the results are truncated.
+fn cloning_search(database: &PaperDatabase, query: &SearchQuery, limit: u8) -> (bool, Vec<Paper>) {
+    let re = regex::RegexBuilder::new(&query.pattern)
+        .case_insensitive(true)
+        .build()
+        .or_else(|_| {
+            regex::RegexBuilder::new(&regex::escape(&query.pattern))
+                .case_insensitive(true)
+                .build()
+        })
+        .expect("Cannot build a regular expression from an escaped pattern");
+
+    let mut result: Vec<&Paper> = database
+        .papers()
+        .filter(|paper| {
+            [&paper.number, &paper.title, &paper.author]
+                .into_iter()
+                .any(|field| matches!(field, Some(field) if re.is_match(field)))
+        })
+        .filter(|paper| query.is_in_date_range(paper.parsed_date))
+        .collect();
+
+    match query.sort {
+        SortOrder::Number => {
+            result.sort_unstable_by(|lhs, rhs| lhs.number.cmp(&rhs.number));
+        }
+        SortOrder::Date => result.sort_unstable_by(|lhs, rhs| {
+            rhs.parsed_date
+                .cmp(&lhs.parsed_date)
+                .then_with(|| lhs.number.cmp(&rhs.number))
+        }),
+    }
+
+    let is_limit_reached = result.len() > limit as usize;
+
+    (
+        is_limit_reached,
+        result.into_iter().take(limit as usize).cloned().collect(),
+    )
+}
+
+fn measure(iterations: u32, mut f: impl FnMut()) -> Measurement {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let started_at = Instant::now();
+
+    for _ in 0..iterations {
+        f();
+    }
+
+    Measurement {
+        allocations: (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / iterations as usize,
+        bytes: (ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes) / iterations as usize,
+        duration: started_at.elapsed() / iterations,
+    }
+}
+
+fn main() {
+    let database = PaperDatabase::from_json(&common::full_size_index()).expect("Invalid index");
+    let queries = ["coroutines", "P2", "year:2022 sort:date", "Barry Revzin"];
+
+    println!(
+        "{:<24} {:>28} {:>28}",
+        "", "borrowed results", "cloned results"
+    );
+    println!(
+        "{:<24} {:>6} {:>10} {:>10} {:>6} {:>10} {:>10}",
+        "query", "allocs", "bytes", "time", "allocs", "bytes", "time"
+    );
+
+    for text in queries {
+        let query: SearchQuery = text.parse().expect("Invalid benchmark query");
+        // Found papers are counted so the searches can't be optimized away
+        let (mut borrowed_found, mut cloned_found) = (0, 0);
+        let borrowed = measure(ITERATIONS, || {
+            let (_, papers) = database.search(&query, LIMIT);
+            borrowed_found += papers.len();
+        });
+        let cloned = measure(ITERATIONS, || {
+            let (_, papers) = cloning_search(&database, &query, LIMIT);
+            cloned_found += papers.len();
+        });
+        assert_eq!(borrowed_found, cloned_found);
+
+        println!(
+            "{:<24} {:>6} {:>10} {:>10.1?} {:>6} {:>10} {:>10.1?}",
+            text,
+            borrowed.allocations,
+            borrowed.bytes,
+            borrowed.duration,
+            cloned.allocations,
+            cloned.bytes,
+            cloned.duration,
+        );
+    }
+}
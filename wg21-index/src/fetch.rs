@@ -0,0 +1,141 @@
+use crate::storage::PaperDatabase;
+
+/// The index of all WG21 papers, drafts and issues maintained by wg21.link.
+pub const WG21_INDEX_URL: &str = "https://wg21.link/index.json";
+
+/// Enough to download the full index over a slow connection.
+pub const DEFAULT_DOWNLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FetchError {
+    /// The index can't be downloaded because of a connection or a server error,
+    /// it's worth retrying later.
+    Network(String),
+    /// The server refused to return the index (HTTP 4xx), e.g. the URI is wrong.
+    /// Retrying won't help.
+    Refused(String),
+    /// The index is downloaded but can't be parsed.
+    Malformed(String),
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::Network(e) => write!(f, "cannot download the index: {}", e),
+            FetchError::Refused(e) => write!(f, "the index request is refused: {}", e),
+            FetchError::Malformed(e) => write!(f, "the index is malformed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Downloads the index as is, e.g. to cache it. [`PaperDatabase::from_json`] parses it.
+/// A download which takes longer than `timeout`, including a stalled one, is a network error.
+pub async fn download_index(
+    uri: url::Url,
+    timeout: std::time::Duration,
+) -> Result<Vec<u8>, FetchError> {
+    let network_error = |e: reqwest::Error| FetchError::Network(e.to_string());
+
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(network_error)?;
+    let response = client.get(uri).send().await.map_err(network_error)?;
+
+    if response.status().is_client_error() {
+        return Err(FetchError::Refused(response.status().to_string()));
+    }
+
+    let body = response
+        .error_for_status()
+        .map_err(network_error)?
+        .bytes()
+        .await
+        .map_err(network_error)?;
+
+    Ok(body.to_vec())
+}
+
+/// Downloads the index in the format of <https://wg21.link/index.json> and builds the database.
+/// `updated_at` of the database is set to the download time.
+pub async fn fetch_index(
+    uri: url::Url,
+    timeout: std::time::Duration,
+) -> Result<PaperDatabase, FetchError> {
+    let body = download_index(uri, timeout).await?;
+
+    let mut database =
+        PaperDatabase::from_json(&body).map_err(|e| FetchError::Malformed(e.to_string()))?;
+    database.set_updated_at(chrono::Utc::now());
+
+    Ok(database)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fetch::{download_index, FetchError, DEFAULT_DOWNLOAD_TIMEOUT};
+    use std::io::{Read, Write};
+    use std::time::Duration;
+
+    // Answers a single HTTP request with the status line and an empty body
+    fn serve_status(status: &'static str) -> url::Url {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = url::Url::parse(&format!(
+            "http://{}/index.json",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 4096]);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn test_download_errors() {
+        assert!(matches!(
+            download_index(serve_status("404 Not Found"), DEFAULT_DOWNLOAD_TIMEOUT).await,
+            Err(FetchError::Refused(_))
+        ));
+        assert!(matches!(
+            download_index(
+                serve_status("503 Service Unavailable"),
+                DEFAULT_DOWNLOAD_TIMEOUT
+            )
+            .await,
+            Err(FetchError::Network(_))
+        ));
+        assert_eq!(
+            download_index(serve_status("200 OK"), DEFAULT_DOWNLOAD_TIMEOUT).await,
+            Ok(Vec::new())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_timeout() {
+        // Connections are queued by the OS, but the request is never answered
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = url::Url::parse(&format!(
+            "http://{}/index.json",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        let started_at = std::time::Instant::now();
+        assert!(matches!(
+            download_index(url, Duration::from_millis(200)).await,
+            Err(FetchError::Network(_))
+        ));
+        assert!(started_at.elapsed() < DEFAULT_DOWNLOAD_TIMEOUT);
+    }
+}
@@ -1,12 +1,13 @@
 use nom::branch::alt;
 use nom::bytes::complete::{tag, tag_no_case, take_while, take_while1};
 use nom::character::is_digit;
-use nom::combinator::opt;
+use nom::combinator::{all_consuming, opt};
 use nom::error::VerboseError;
 use nom::multi::many0;
 use nom::sequence::tuple;
 use nom::IResult;
 
+/// A paper mentioned in a text in brackets, e.g. `[P2300R7]`, `{N4861}` or `<CWG2518>`.
 #[derive(Debug, Eq, PartialEq)]
 pub struct ImplicitPaperSearchRequest {
     pub paper_type: String,
@@ -14,8 +15,21 @@ pub struct ImplicitPaperSearchRequest {
     pub revision_number: Option<i32>,
 }
 
-pub fn is_char_digit(chr: char) -> bool {
-    return chr.is_ascii() && is_digit(chr as u8);
+impl ImplicitPaperSearchRequest {
+    /// The pattern for `PaperDatabase::search_by_number`, e.g. "P2300" or "P2300r7".
+    pub fn search_pattern(&self) -> String {
+        let mut pattern = format!("{}{}", self.paper_type, self.paper_number);
+
+        if let Some(revision_number) = self.revision_number {
+            pattern.push_str(format!("r{}", revision_number).as_str());
+        }
+
+        pattern
+    }
+}
+
+pub(crate) fn is_char_digit(chr: char) -> bool {
+    chr.is_ascii() && is_digit(chr as u8)
 }
 
 fn is_not_start_request_character(chr: char) -> bool {
@@ -93,13 +107,15 @@ fn paper(input: &str) -> IResult<&str, ImplicitPaperSearchRequest, VerboseError<
     }
 }
 
-pub fn paper_request(input: &str) -> IResult<&str, ImplicitPaperSearchRequest, VerboseError<&str>> {
+pub(crate) fn paper_request(
+    input: &str,
+) -> IResult<&str, ImplicitPaperSearchRequest, VerboseError<&str>> {
     let (input, (_, parsed_paper, _)) =
         tuple((start_paper_search_request, paper, end_paper_search_request))(input)?;
     Ok((input, parsed_paper))
 }
 
-pub fn paper_request_with_leading_trash(
+pub(crate) fn paper_request_with_leading_trash(
     input: &str,
 ) -> IResult<&str, ImplicitPaperSearchRequest, VerboseError<&str>> {
     let (input, (_, paper_request)) =
@@ -108,19 +124,34 @@ pub fn paper_request_with_leading_trash(
     Ok((input, paper_request))
 }
 
-pub fn many_paper_requests(
+pub(crate) fn many_paper_requests(
     input: &str,
 ) -> IResult<&str, Vec<ImplicitPaperSearchRequest>, VerboseError<&str>> {
     many0(paper_request_with_leading_trash)(input)
 }
 
+/// Finds all paper requests in a text, e.g. a chat message, in the order they are mentioned.
+pub fn find_paper_requests(text: &str) -> Vec<ImplicitPaperSearchRequest> {
+    // many0 stops before the first fragment without a request, so it doesn't fail on any text
+    many_paper_requests(text)
+        .map(|(_, requests)| requests)
+        .unwrap_or_default()
+}
+
+/// Parses a whole text as a paper number without brackets, e.g. "P2300R7" or "n4861".
+pub fn parse_paper_number(input: &str) -> Option<ImplicitPaperSearchRequest> {
+    all_consuming(paper)(input.trim())
+        .ok()
+        .map(|(_, request)| request)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::implicit_search_request_parser::{
-        end_paper_search_request, is_char_digit, is_not_start_request_character,
-        many_paper_requests, paper, paper_number, paper_request, paper_request_with_leading_trash,
-        paper_type, revision, revision_number, start_paper_search_request,
-        ImplicitPaperSearchRequest,
+        end_paper_search_request, find_paper_requests, is_char_digit,
+        is_not_start_request_character, many_paper_requests, paper, paper_number, paper_request,
+        paper_request_with_leading_trash, paper_type, parse_paper_number, revision,
+        revision_number, start_paper_search_request, ImplicitPaperSearchRequest,
     };
     use nom::error::ErrorKind::{Alt, Tag, TakeWhile1};
     use nom::error::VerboseError;
@@ -360,4 +391,35 @@ mod tests {
 
         assert_eq!(many_paper_requests("some_text"), Ok(("some_text", vec![])));
     }
+
+    #[test]
+    fn test_find_paper_requests() {
+        let patterns = |text| {
+            find_paper_requests(text)
+                .iter()
+                .map(ImplicitPaperSearchRequest::search_pattern)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            patterns("Сравните [P2300R7], {n4861} и <CWG2518> [P] {"),
+            vec!["P2300r7", "n4861", "CWG2518"]
+        );
+        assert!(patterns("").is_empty());
+        assert!(patterns("P2300 without brackets").is_empty());
+    }
+
+    #[test]
+    fn test_parse_paper_number() {
+        let request = parse_paper_number(" p2300R7 ").unwrap();
+        assert_eq!(request.search_pattern(), "p2300r7");
+        assert_eq!(
+            parse_paper_number("N4861").unwrap().search_pattern(),
+            "N4861"
+        );
+
+        assert_eq!(parse_paper_number("[P2300]"), None);
+        assert_eq!(parse_paper_number("P2300 and more"), None);
+        assert_eq!(parse_paper_number("executors"), None);
+    }
 }
@@ -0,0 +1,36 @@
+//! Search in the [wg21.link](https://wg21.link) index of C++ proposals: papers, drafts and issues.
+//!
+//! The index is loaded from JSON with [`PaperDatabase::from_json`] or downloaded with
+//! [`fetch::fetch_index`]. Papers are found by number with [`PaperDatabase::resolve`] and
+//! [`PaperDatabase::search_by_number`], or by number, title and author with [`PaperDatabase::search`].
+//! [`implicit_search_request_parser::find_paper_requests`] finds paper numbers mentioned in a text
+//! like "see `[P2300R7]`", [`PaperDatabase::search_requested_papers`] finds papers for them.
+//! [`render`] turns found papers into MarkdownV2, HTML or plain text.
+//!
+//! ```
+//! use wg21_index::{PaperDatabase, SearchQuery};
+//!
+//! let database = PaperDatabase::from_json(
+//!     br#"{
+//!         "P2300R7": {"title": "std::execution", "author": "Eric Niebler", "date": "2023-04-21"},
+//!         "P2300R6": {"title": "std::execution", "author": "Eric Niebler", "date": "2023-01-19"}
+//!     }"#,
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(database.resolve("p2300").unwrap().number.as_deref(), Some("P2300R7"));
+//!
+//! let query: SearchQuery = "execution since:2023-03".parse().unwrap();
+//! let (is_limit_reached, papers) = database.search(&query, 20);
+//! assert!(!is_limit_reached);
+//! assert_eq!(papers.len(), 1);
+//! ```
+
+pub mod fetch;
+pub mod implicit_search_request_parser;
+pub mod render;
+pub mod search_query;
+pub mod storage;
+
+pub use search_query::SearchQuery;
+pub use storage::{Paper, PaperDatabase};
@@ -0,0 +1,322 @@
+use crate::storage::Paper;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Turns papers and plain service messages into text, e.g. a message sent to a chat.
+///
+/// Implementations only provide the escaping primitives and the format,
+/// the layout of a paper line is shared between all of them.
+pub trait PaperRenderer: Send + Sync {
+    /// The markup of the rendered text, a chat has to be told how to show it.
+    fn format(&self) -> RenderFormat;
+
+    /// Escapes arbitrary text so it is shown literally.
+    fn escape(&self, text: &str) -> String;
+
+    /// Renders a link with an already unescaped text and URI.
+    fn link(&self, text: &str, uri: &str) -> String;
+
+    fn bold(&self, text: &str) -> String;
+
+    fn render_paper(&self, paper: &Paper) -> String {
+        let title = paper.display_title();
+
+        let mut result = match &paper.link {
+            Some(link) => self.link(&title, link),
+            None => self.escape(&title),
+        };
+
+        if let Some(author) = &paper.author {
+            result.push_str(&self.escape(&format!(" (by {})", author)));
+        }
+
+        if let Some(date) = &paper.date {
+            result.push_str(&self.escape(&format!(" ({})", date)));
+        }
+
+        if let Some(github_url) = &paper.github_url {
+            result.push_str(&self.escape(" (Related: "));
+            result.push_str(&self.link("GitHub issue", github_url));
+            result.push_str(&self.escape(")"));
+        }
+
+        result
+    }
+}
+
+pub struct MarkdownV2Renderer;
+
+impl PaperRenderer for MarkdownV2Renderer {
+    fn format(&self) -> RenderFormat {
+        RenderFormat::MarkdownV2
+    }
+
+    fn escape(&self, text: &str) -> String {
+        markdown_v2_escape(text)
+    }
+
+    fn link(&self, text: &str, uri: &str) -> String {
+        format!(
+            "[{}]({})",
+            markdown_v2_escape(text),
+            markdown_v2_escape_inline_uri(uri)
+        )
+    }
+
+    fn bold(&self, text: &str) -> String {
+        format!("*{}*", markdown_v2_escape(text))
+    }
+}
+
+pub struct HtmlRenderer;
+
+impl PaperRenderer for HtmlRenderer {
+    fn format(&self) -> RenderFormat {
+        RenderFormat::Html
+    }
+
+    fn escape(&self, text: &str) -> String {
+        html_escape(text)
+    }
+
+    fn link(&self, text: &str, uri: &str) -> String {
+        format!("<a href=\"{}\">{}</a>", html_escape(uri), html_escape(text))
+    }
+
+    fn bold(&self, text: &str) -> String {
+        format!("<b>{}</b>", html_escape(text))
+    }
+}
+
+pub struct PlainTextRenderer;
+
+impl PaperRenderer for PlainTextRenderer {
+    fn format(&self) -> RenderFormat {
+        RenderFormat::Plain
+    }
+
+    fn escape(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn link(&self, text: &str, uri: &str) -> String {
+        format!("{} <{}>", text, uri)
+    }
+
+    fn bold(&self, text: &str) -> String {
+        text.to_string()
+    }
+}
+
+/// Output format which can be selected per chat.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderFormat {
+    MarkdownV2,
+    Html,
+    Plain,
+}
+
+impl RenderFormat {
+    pub fn renderer(&self) -> &'static dyn PaperRenderer {
+        match self {
+            RenderFormat::MarkdownV2 => &MarkdownV2Renderer,
+            RenderFormat::Html => &HtmlRenderer,
+            RenderFormat::Plain => &PlainTextRenderer,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderFormat::MarkdownV2 => "markdownv2",
+            RenderFormat::Html => "html",
+            RenderFormat::Plain => "plain",
+        }
+    }
+}
+
+impl FromStr for RenderFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "markdownv2" | "markdown" => Ok(RenderFormat::MarkdownV2),
+            "html" => Ok(RenderFormat::Html),
+            "plain" | "text" => Ok(RenderFormat::Plain),
+            other => Err(anyhow::anyhow!("Unknown output format: {}", other)),
+        }
+    }
+}
+
+/// Renders papers in the given order, searches are responsible for sorting them.
+pub fn convert_papers_to_result(papers: &[&Paper], renderer: &dyn PaperRenderer) -> String {
+    let mut formatted_papers = Vec::<String>::with_capacity(papers.len());
+
+    for paper in papers {
+        formatted_papers.push(renderer.render_paper(paper))
+    }
+
+    formatted_papers.join("\n\n")
+}
+
+/// Escapes text outside of any entity according to the MarkdownV2 rules.
+pub fn markdown_v2_escape(text: &str) -> String {
+    escape_chars(text, |chr| {
+        matches!(
+            chr,
+            '_' | '*'
+                | '['
+                | ']'
+                | '('
+                | ')'
+                | '~'
+                | '`'
+                | '>'
+                | '#'
+                | '+'
+                | '-'
+                | '='
+                | '|'
+                | '{'
+                | '}'
+                | '.'
+                | '!'
+                | '\\'
+        )
+    })
+}
+
+/// Escapes the URI part of an inline link `[text](URI)` according to the MarkdownV2 rules.
+pub fn markdown_v2_escape_inline_uri(text: &str) -> String {
+    escape_chars(text, |chr| chr == ')' || chr == '\\')
+}
+
+pub fn html_escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for chr in text.chars() {
+        match chr {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(chr),
+        }
+    }
+
+    result
+}
+
+fn escape_chars(text: &str, needs_escaping: impl Fn(char) -> bool) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for chr in text.chars() {
+        if needs_escaping(chr) {
+            result.push('\\');
+        }
+        result.push(chr);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::{
+        html_escape, markdown_v2_escape, markdown_v2_escape_inline_uri, RenderFormat,
+    };
+    use crate::storage::Paper;
+
+    fn full_paper() -> Paper {
+        Paper {
+            number: Some("P1000R4".to_string()),
+            title: Some("C++ IS schedule (<draft>) & more_stuff!".to_string()),
+            link: Some("https://wg21.link/p1000r4".to_string()),
+            author: Some("Herb Sutter".to_string()),
+            date: Some("2020-02-14".to_string()),
+            github_url: Some("https://github.com/cplusplus/papers/issues/(1)".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn bare_paper() -> Paper {
+        Paper::default()
+    }
+
+    #[test]
+    fn test_markdown_v2_escape() {
+        assert_eq!(
+            markdown_v2_escape(r"_*[]()~`>#+-=|{}.!\"),
+            r"\_\*\[\]\(\)\~\`\>\#\+\-\=\|\{\}\.\!\\"
+        );
+        assert_eq!(markdown_v2_escape("Привет"), "Привет");
+    }
+
+    #[test]
+    fn test_markdown_v2_escape_inline_uri() {
+        assert_eq!(
+            markdown_v2_escape_inline_uri(r"https://a.b/c_(d)\e.f"),
+            r"https://a.b/c_(d\)\\e.f"
+        );
+    }
+
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(
+            html_escape(r#"<a href="x">&</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_markdown_v2_golden() {
+        let renderer = RenderFormat::MarkdownV2.renderer();
+
+        assert_eq!(
+            renderer.render_paper(&full_paper()),
+            r"[P1000R4: C\+\+ IS schedule \(<draft\>\) & more\_stuff\!](https://wg21.link/p1000r4) \(by Herb Sutter\) \(2020\-02\-14\) \(Related: [GitHub issue](https://github.com/cplusplus/papers/issues/(1\))\)"
+        );
+        assert_eq!(
+            renderer.render_paper(&bare_paper()),
+            "Here should be a paper title"
+        );
+    }
+
+    #[test]
+    fn test_html_golden() {
+        let renderer = RenderFormat::Html.renderer();
+
+        assert_eq!(
+            renderer.render_paper(&full_paper()),
+            r#"<a href="https://wg21.link/p1000r4">P1000R4: C++ IS schedule (&lt;draft&gt;) &amp; more_stuff!</a> (by Herb Sutter) (2020-02-14) (Related: <a href="https://github.com/cplusplus/papers/issues/(1)">GitHub issue</a>)"#
+        );
+        assert_eq!(
+            renderer.render_paper(&bare_paper()),
+            "Here should be a paper title"
+        );
+    }
+
+    #[test]
+    fn test_plain_golden() {
+        let renderer = RenderFormat::Plain.renderer();
+
+        assert_eq!(
+            renderer.render_paper(&full_paper()),
+            "P1000R4: C++ IS schedule (<draft>) & more_stuff! <https://wg21.link/p1000r4> (by Herb Sutter) (2020-02-14) (Related: GitHub issue <https://github.com/cplusplus/papers/issues/(1)>)"
+        );
+    }
+
+    #[test]
+    fn test_render_format_from_str() {
+        assert_eq!("html".parse::<RenderFormat>().unwrap(), RenderFormat::Html);
+        assert_eq!(
+            "MarkdownV2".parse::<RenderFormat>().unwrap(),
+            RenderFormat::MarkdownV2
+        );
+        assert_eq!(
+            "plain".parse::<RenderFormat>().unwrap(),
+            RenderFormat::Plain
+        );
+        assert!("bbcode".parse::<RenderFormat>().is_err());
+    }
+}
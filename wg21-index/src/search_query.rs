@@ -0,0 +1,200 @@
+use chrono::{Datelike, NaiveDate};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SortOrder {
+    #[default]
+    Number,
+    /// The most recent papers first.
+    Date,
+}
+
+/// A free-text search request with optional filters, e.g.
+/// "coroutines since:2022 until:2023-06 sort:date" or "executors year:2021".
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    /// Inclusive lower bound of the paper date.
+    pub since: Option<NaiveDate>,
+    /// Inclusive upper bound of the paper date.
+    pub until: Option<NaiveDate>,
+    pub sort: SortOrder,
+}
+
+impl SearchQuery {
+    pub fn has_date_filter(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    /// Papers without a date never match a query with a date filter.
+    pub fn is_in_date_range(&self, date: Option<NaiveDate>) -> bool {
+        if !self.has_date_filter() {
+            return true;
+        }
+
+        match date {
+            Some(date) => {
+                !matches!(self.since, Some(since) if date < since)
+                    && !matches!(self.until, Some(until) if date > until)
+            }
+            None => false,
+        }
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut query = SearchQuery::default();
+        let mut pattern_words = Vec::new();
+
+        for word in s.split_whitespace() {
+            match word.split_once(':') {
+                Some(("since", value)) => {
+                    query.since = Some(parse_date_bound(value, DateBound::Start)?);
+                }
+                Some(("until", value)) => {
+                    query.until = Some(parse_date_bound(value, DateBound::End)?);
+                }
+                Some(("year", value)) => {
+                    let year = value
+                        .parse::<i32>()
+                        .map_err(|_| anyhow::anyhow!("Cannot parse year: {}", value))?;
+                    query.since = Some(parse_date_bound(&year.to_string(), DateBound::Start)?);
+                    query.until = Some(parse_date_bound(&year.to_string(), DateBound::End)?);
+                }
+                Some(("sort", "date")) => query.sort = SortOrder::Date,
+                Some(("sort", "number")) => query.sort = SortOrder::Number,
+                Some(("sort", value)) => {
+                    return Err(anyhow::anyhow!("Unknown sort order: {}", value));
+                }
+                _ => pattern_words.push(word),
+            }
+        }
+
+        query.pattern = pattern_words.join(" ");
+
+        if query.pattern.is_empty() && !query.has_date_filter() {
+            return Err(anyhow::anyhow!("Empty search query"));
+        }
+
+        Ok(query)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum DateBound {
+    Start,
+    End,
+}
+
+/// Parses "YYYY", "YYYY-MM" or "YYYY-MM-DD" into the first or the last day of the period.
+fn parse_date_bound(value: &str, bound: DateBound) -> anyhow::Result<NaiveDate> {
+    let error = || anyhow::anyhow!("Cannot parse date: {}", value);
+
+    let parts = value
+        .split('-')
+        .map(|part| part.parse::<u32>().map_err(|_| error()))
+        .collect::<anyhow::Result<Vec<u32>>>()?;
+
+    let date = match (parts.as_slice(), bound) {
+        ([year], DateBound::Start) => NaiveDate::from_ymd_opt(*year as i32, 1, 1),
+        ([year], DateBound::End) => NaiveDate::from_ymd_opt(*year as i32, 12, 31),
+        ([year, month], DateBound::Start) => NaiveDate::from_ymd_opt(*year as i32, *month, 1),
+        ([year, month], DateBound::End) => {
+            NaiveDate::from_ymd_opt(*year as i32, *month, 1).and_then(last_day_of_month)
+        }
+        ([year, month, day], _) => NaiveDate::from_ymd_opt(*year as i32, *month, *day),
+        _ => None,
+    };
+
+    date.ok_or_else(error)
+}
+
+fn last_day_of_month(first_day: NaiveDate) -> Option<NaiveDate> {
+    let (year, month) = if first_day.month() == 12 {
+        (first_day.year() + 1, 1)
+    } else {
+        (first_day.year(), first_day.month() + 1)
+    };
+
+    NaiveDate::from_ymd_opt(year, month, 1)?.pred_opt()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search_query::{SearchQuery, SortOrder};
+    use chrono::NaiveDate;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn test_plain_query() {
+        assert_eq!(
+            "executors for C++".parse::<SearchQuery>().unwrap(),
+            SearchQuery {
+                pattern: "executors for C++".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(
+            "coroutines since:2022 until:2023-02 sort:date"
+                .parse::<SearchQuery>()
+                .unwrap(),
+            SearchQuery {
+                pattern: "coroutines".to_string(),
+                since: date(2022, 1, 1),
+                until: date(2023, 2, 28),
+                sort: SortOrder::Date,
+            }
+        );
+        assert_eq!(
+            "year:2024".parse::<SearchQuery>().unwrap(),
+            SearchQuery {
+                pattern: "".to_string(),
+                since: date(2024, 1, 1),
+                until: date(2024, 12, 31),
+                sort: SortOrder::Number,
+            }
+        );
+        assert_eq!(
+            "until:2023-12 since:2023-03-15"
+                .parse::<SearchQuery>()
+                .unwrap(),
+            SearchQuery {
+                pattern: "".to_string(),
+                since: date(2023, 3, 15),
+                until: date(2023, 12, 31),
+                sort: SortOrder::Number,
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_queries() {
+        assert!("".parse::<SearchQuery>().is_err());
+        assert!("sort:date".parse::<SearchQuery>().is_err());
+        assert!("x since:yesterday".parse::<SearchQuery>().is_err());
+        assert!("x until:2023-13".parse::<SearchQuery>().is_err());
+        assert!("x sort:title".parse::<SearchQuery>().is_err());
+    }
+
+    #[test]
+    fn test_is_in_date_range() {
+        let query = "x since:2022 until:2022".parse::<SearchQuery>().unwrap();
+
+        assert!(query.is_in_date_range(date(2022, 6, 1)));
+        assert!(query.is_in_date_range(date(2022, 12, 31)));
+        assert!(!query.is_in_date_range(date(2023, 1, 1)));
+        assert!(!query.is_in_date_range(None));
+
+        assert!("x".parse::<SearchQuery>().unwrap().is_in_date_range(None));
+    }
+}
@@ -0,0 +1,810 @@
+use serde::{Deserialize, Serialize};
+
+use crate::implicit_search_request_parser::ImplicitPaperSearchRequest;
+use crate::search_query::{SearchQuery, SortOrder};
+
+use std::collections::{BTreeMap, HashMap};
+
+/// An entry of the wg21.link index: a paper, a draft or an issue. All fields except the
+/// number come from the index as is, the number is the key of the entry.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Paper {
+    pub number: Option<String>,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub github_url: Option<String>,
+    pub long_link: Option<String>,
+    pub subgroup: Option<String>,
+    pub status: Option<String>,
+    pub mailing: Option<String>,
+    /// Individual author names split from `author` when the database is built.
+    #[serde(skip)]
+    pub authors: Vec<String>,
+    /// `date` parsed when the database is built.
+    #[serde(skip)]
+    pub parsed_date: Option<chrono::NaiveDate>,
+}
+
+impl Paper {
+    /// Returns "number: title", falling back to whichever of them is present.
+    pub fn display_title(&self) -> String {
+        match (&self.number, &self.title) {
+            (Some(number), Some(title)) => format!("{}: {}", number, title),
+            (Some(number), None) => number.clone(),
+            (None, Some(title)) => title.clone(),
+            // In case if number and title are both empty - just fill with some placeholder
+            (None, None) => "Here should be a paper title".to_string(),
+        }
+    }
+
+    /// Returns the mailing ("YYYY-MM") the paper was published in: the explicit one if the
+    /// index provides a valid one, otherwise the month of the paper date.
+    pub fn mailing(&self) -> Option<String> {
+        if let Some(mailing) = self.mailing.as_deref().and_then(normalize_mailing_name) {
+            return Some(mailing.to_string());
+        }
+
+        self.parsed_date
+            .map(|date| date.format("%Y-%m").to_string())
+    }
+
+    /// Checks whether the paper targets `subgroup`, e.g. "LEWG". Papers may target several subgroups.
+    pub fn is_in_subgroup(&self, subgroup: &str) -> bool {
+        match &self.subgroup {
+            Some(subgroups) => subgroups
+                .split(|chr: char| !chr.is_ascii_alphanumeric())
+                .any(|paper_subgroup| paper_subgroup.eq_ignore_ascii_case(subgroup)),
+            None => false,
+        }
+    }
+}
+
+/// Parses a paper date, the index uses the "YYYY-MM-DD" format.
+pub fn parse_paper_date(date: &str) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+}
+
+/// Splits an author string like "A. Smith, B. Jones and C. Brown" into individual names.
+pub fn split_authors(author: &str) -> Vec<String> {
+    author
+        .split([',', ';', '&', '\n'])
+        .flat_map(|part| part.split(" and "))
+        .map(|name| name.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case("et al."))
+        .collect()
+}
+
+/// Returns the key used to match author names: lowercase without punctuation.
+pub fn normalize_author(name: &str) -> String {
+    name.split(|chr: char| chr.is_whitespace() || chr == '.')
+        .filter(|part| !part.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// An author with all papers they have written.
+pub struct Author {
+    pub name: String,
+    pub normalized_name: String,
+    /// Numbers of all papers (with all revisions) the author has written.
+    pub numbers: Vec<String>,
+}
+
+/// Checks whether `text` looks like a mailing name, e.g. "2024-10".
+pub fn is_mailing_name(text: &str) -> bool {
+    let bytes = text.as_bytes();
+
+    bytes.len() == 7
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+        && bytes[4] == b'-'
+        && bytes[5..].iter().all(u8::is_ascii_digit)
+}
+
+/// Returns the mailing name ("YYYY-MM") from `text`: either the name itself or the
+/// "mailingYYYY-MM" form the index uses. `None` if `text` is not a mailing name.
+pub fn normalize_mailing_name(text: &str) -> Option<&str> {
+    let name = text.strip_prefix("mailing").unwrap_or(text);
+
+    is_mailing_name(name).then_some(name)
+}
+
+// Only N- and P-papers are published in mailings, drafts and issues are not
+fn is_mailing_document(number: &str) -> bool {
+    let mut chars = number.chars();
+
+    matches!(chars.next(), Some('N' | 'n' | 'P' | 'p'))
+        && matches!(chars.next(), Some(chr) if chr.is_ascii_digit())
+}
+
+/// Splits a paper number like "P2300R7" into the base number "P2300" and the revision 7.
+pub fn split_revision(number: &str) -> (&str, Option<u32>) {
+    if let Some(position) = number.rfind(['R', 'r']) {
+        let (base, revision) = (&number[..position], &number[position + 1..]);
+        let base_has_digits = base.ends_with(|chr: char| chr.is_ascii_digit());
+
+        if base_has_digits {
+            if let Ok(revision) = revision.parse() {
+                return (base, Some(revision));
+            }
+        }
+    }
+
+    (number, None)
+}
+
+/// The wg21.link index prepared for searches: papers by number, mailings and authors.
+pub struct PaperDatabase {
+    database: HashMap<String, Paper>,
+    /// Paper numbers grouped by mailing, sorted by number inside a mailing.
+    mailings: BTreeMap<String, Vec<String>>,
+    /// Authors by their normalized names.
+    authors: BTreeMap<String, Author>,
+    /// When the database was successfully fetched, `None` until the first update.
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl PaperDatabase {
+    /// An empty database, e.g. until the index is fetched for the first time.
+    pub fn new_empty() -> Self {
+        PaperDatabase {
+            database: HashMap::<String, Paper>::new(),
+            mailings: BTreeMap::new(),
+            authors: BTreeMap::new(),
+            updated_at: None,
+        }
+    }
+
+    /// Builds the database from index entries keyed by paper number.
+    pub fn new(mut initial_values: HashMap<String, Paper>) -> Self {
+        let mut mailings = BTreeMap::<String, Vec<String>>::new();
+        let mut authors = BTreeMap::<String, Author>::new();
+
+        for (key, value) in initial_values.iter_mut() {
+            value.number = Option::from(key.clone());
+            value.authors = split_authors(value.author.as_deref().unwrap_or_default());
+            value.parsed_date = value.date.as_deref().and_then(parse_paper_date);
+
+            for name in &value.authors {
+                let normalized_name = normalize_author(name);
+
+                authors
+                    .entry(normalized_name.clone())
+                    .or_insert_with(|| Author {
+                        name: name.clone(),
+                        normalized_name,
+                        numbers: Vec::new(),
+                    })
+                    .numbers
+                    .push(key.clone());
+            }
+
+            if is_mailing_document(key) {
+                if let Some(mailing) = value.mailing() {
+                    mailings.entry(mailing).or_default().push(key.clone());
+                }
+            }
+        }
+
+        for numbers in mailings.values_mut() {
+            numbers.sort_unstable();
+        }
+
+        PaperDatabase {
+            database: initial_values,
+            mailings,
+            authors,
+            updated_at: None,
+        }
+    }
+
+    /// Parses the index in the format of <https://wg21.link/index.json>.
+    pub fn from_json(json: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice::<HashMap<String, Paper>>(json).map(PaperDatabase::new)
+    }
+
+    /// When the index was fetched, it is set by the code fetching it.
+    pub fn updated_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.updated_at
+    }
+
+    pub fn set_updated_at(&mut self, updated_at: chrono::DateTime<chrono::Utc>) {
+        self.updated_at = Some(updated_at);
+    }
+
+    /// All entries of the index in no particular order.
+    pub fn papers(&self) -> impl Iterator<Item = &Paper> {
+        self.database.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.database.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.database.is_empty()
+    }
+
+    /// Finds a paper by its exact number. If the number has no revision and there is
+    /// no such entry, the latest revision is returned.
+    pub fn resolve(&self, number: &str) -> Option<&Paper> {
+        let number = number.trim().to_uppercase();
+
+        if let Some(paper) = self.database.get(&number) {
+            return Some(paper);
+        }
+
+        match split_revision(&number) {
+            (_, Some(_)) => None,
+            (base, None) => self.revisions(base).pop(),
+        }
+    }
+
+    /// Returns all revisions of a paper ordered from the oldest to the latest one.
+    pub fn revisions(&self, base_number: &str) -> Vec<&Paper> {
+        let base_number = base_number.to_uppercase();
+
+        let mut revisions: Vec<(u32, &Paper)> = self
+            .database
+            .iter()
+            .filter_map(|(number, paper)| match split_revision(number) {
+                (base, Some(revision)) if base.eq_ignore_ascii_case(&base_number) => {
+                    Some((revision, paper))
+                }
+                _ => None,
+            })
+            .collect();
+
+        revisions.sort_unstable_by_key(|(revision, _)| *revision);
+
+        revisions.into_iter().map(|(_, paper)| paper).collect()
+    }
+
+    /// The most recent mailing name, e.g. "2024-10".
+    pub fn latest_mailing(&self) -> Option<&str> {
+        self.mailings.keys().next_back().map(String::as_str)
+    }
+
+    /// Returns papers of a mailing, optionally only the ones targeting `subgroup`.
+    pub fn mailing_papers(&self, mailing: &str, subgroup: Option<&str>) -> Vec<&Paper> {
+        self.mailings
+            .get(mailing)
+            .map(|numbers| {
+                numbers
+                    .iter()
+                    .filter_map(|number| self.database.get(number))
+                    .filter(|paper| match subgroup {
+                        Some(subgroup) => paper.is_in_subgroup(subgroup),
+                        None => true,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Finds authors by a name or its part. An exact match of the whole normalized name
+    /// wins over partial matches, so "John Smith" is not ambiguous with "John Smithson".
+    pub fn find_authors(&self, query: &str) -> Vec<&Author> {
+        let query = normalize_author(query);
+
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        if let Some(author) = self.authors.get(&query) {
+            return vec![author];
+        }
+
+        let query_words: Vec<&str> = query.split(' ').collect();
+
+        self.authors
+            .values()
+            .filter(|author| {
+                let words: Vec<&str> = author.normalized_name.split(' ').collect();
+                query_words
+                    .iter()
+                    .all(|query_word| words.iter().any(|word| word.starts_with(query_word)))
+            })
+            .collect()
+    }
+
+    /// Finds an author by a name normalized with [`normalize_author`].
+    pub fn author(&self, normalized_name: &str) -> Option<&Author> {
+        self.authors.get(normalized_name)
+    }
+
+    /// Returns the latest revision of every paper of the author, the most recent first.
+    pub fn author_papers(&self, author: &Author) -> Vec<&Paper> {
+        let mut latest_revisions = HashMap::<&str, (Option<u32>, &Paper)>::new();
+
+        for number in &author.numbers {
+            if let Some(paper) = self.database.get(number) {
+                let (base_number, revision) = split_revision(number);
+
+                let latest = latest_revisions
+                    .entry(base_number)
+                    .or_insert((revision, paper));
+                if revision > latest.0 {
+                    *latest = (revision, paper);
+                }
+            }
+        }
+
+        let mut papers: Vec<&Paper> = latest_revisions
+            .into_values()
+            .map(|(_, paper)| paper)
+            .collect();
+
+        papers.sort_unstable_by(|lhs, rhs| {
+            rhs.parsed_date
+                .cmp(&lhs.parsed_date)
+                .then_with(|| lhs.number.cmp(&rhs.number))
+        });
+
+        papers
+    }
+
+    /// Numbers of all entries of the index.
+    pub fn numbers(&self) -> std::collections::HashSet<&str> {
+        self.database.keys().map(String::as_str).collect()
+    }
+
+    /// Searches papers by number matching `pattern`, a case insensitive regular expression or a
+    /// literal text. Returns at most `limit` papers sorted by number and whether there were more.
+    pub fn search_by_number(&self, pattern: &str, limit: u8) -> (bool, Vec<&Paper>) {
+        let re = build_search_regex(pattern);
+
+        let mut result: Vec<&Paper> = self
+            .database
+            .values()
+            .filter(|paper| matches!(&paper.number, Some(number) if re.is_match(number)))
+            .collect();
+
+        result.sort_unstable_by(|lhs, rhs| lhs.number.cmp(&rhs.number));
+
+        truncate_results(result, limit)
+    }
+
+    /// Finds papers for paper requests the way they are answered in chats: every request matches
+    /// all revisions of the paper, at most `limit` papers in total. Returns whether the result is truncated.
+    pub fn search_requested_papers(
+        &self,
+        paper_requests: &[ImplicitPaperSearchRequest],
+        limit: u8,
+    ) -> (bool, Vec<&Paper>) {
+        let mut result_papers = Vec::<&Paper>::new();
+        let mut is_result_truncated = false;
+
+        for paper_request in paper_requests {
+            let (is_result_truncated_t, found_papers) =
+                self.search_by_number(&paper_request.search_pattern(), limit);
+
+            is_result_truncated = is_result_truncated_t || is_result_truncated;
+
+            for paper in found_papers {
+                result_papers.push(paper);
+
+                if result_papers.len() == limit as usize {
+                    is_result_truncated = true;
+                    break;
+                }
+            }
+
+            if is_result_truncated {
+                break;
+            }
+        }
+
+        (is_result_truncated, result_papers)
+    }
+
+    /// Searches papers by number, title or author matching the query pattern and
+    /// published in the query date range. Returns at most `limit` papers ordered as the query
+    /// asks and whether there were more. Found papers are borrowed from the database.
+    pub fn search(&self, query: &SearchQuery, limit: u8) -> (bool, Vec<&Paper>) {
+        let re = build_search_regex(&query.pattern);
+
+        let mut result: Vec<&Paper> = self
+            .database
+            .values()
+            .filter(|paper| {
+                [&paper.number, &paper.title, &paper.author]
+                    .into_iter()
+                    .any(|field| matches!(field, Some(field) if re.is_match(field)))
+            })
+            .filter(|paper| query.is_in_date_range(paper.parsed_date))
+            .collect();
+
+        match query.sort {
+            SortOrder::Number => {
+                result.sort_unstable_by(|lhs, rhs| lhs.number.cmp(&rhs.number));
+            }
+            // The most recent first, papers without a date go last
+            SortOrder::Date => result.sort_unstable_by(|lhs, rhs| {
+                rhs.parsed_date
+                    .cmp(&lhs.parsed_date)
+                    .then_with(|| lhs.number.cmp(&rhs.number))
+            }),
+        }
+
+        truncate_results(result, limit)
+    }
+}
+
+// A pattern which is not a valid regular expression is searched for literally
+fn build_search_regex(pattern: &str) -> regex::Regex {
+    regex::RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .or_else(|_| {
+            regex::RegexBuilder::new(&regex::escape(pattern))
+                .case_insensitive(true)
+                .build()
+        })
+        .expect("Cannot build a regular expression from an escaped pattern")
+}
+
+fn truncate_results(mut result: Vec<&Paper>, limit: u8) -> (bool, Vec<&Paper>) {
+    let is_limit_reached = result.len() > limit as usize;
+    result.truncate(limit as usize);
+
+    (is_limit_reached, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::implicit_search_request_parser::find_paper_requests;
+    use crate::search_query::SearchQuery;
+    use crate::storage::{
+        is_mailing_name, normalize_author, normalize_mailing_name, parse_paper_date, split_authors,
+        split_revision, Author, Paper, PaperDatabase,
+    };
+    use std::collections::HashMap;
+
+    fn database(numbers: &[&str]) -> PaperDatabase {
+        PaperDatabase::new(
+            numbers
+                .iter()
+                .map(|number| (number.to_string(), Paper::default()))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn test_split_revision() {
+        assert_eq!(split_revision("P2300R7"), ("P2300", Some(7)));
+        assert_eq!(split_revision("P2300r10"), ("P2300", Some(10)));
+        assert_eq!(split_revision("P2300"), ("P2300", None));
+        assert_eq!(split_revision("N4861"), ("N4861", None));
+        assert_eq!(split_revision("EDIT1"), ("EDIT1", None));
+        assert_eq!(split_revision("SD6"), ("SD6", None));
+    }
+
+    #[test]
+    fn test_mailing() {
+        let dated = |date: &str| Paper {
+            date: Some(date.to_string()),
+            parsed_date: parse_paper_date(date),
+            ..Default::default()
+        };
+
+        assert_eq!(dated("2024-10-15").mailing(), Some("2024-10".to_string()));
+        assert_eq!(dated("2024").mailing(), None);
+        assert_eq!(dated("unknown date").mailing(), None);
+        assert_eq!(Paper::default().mailing(), None);
+
+        let explicit = Paper {
+            mailing: Some("2024-10".to_string()),
+            ..dated("2024-09-30")
+        };
+        assert_eq!(explicit.mailing(), Some("2024-10".to_string()));
+
+        let invalid = Paper {
+            mailing: Some("autumn".to_string()),
+            ..dated("2024-09-30")
+        };
+        assert_eq!(invalid.mailing(), Some("2024-09".to_string()));
+
+        assert!(is_mailing_name("2024-10"));
+        assert!(!is_mailing_name("2024-1"));
+        assert!(!is_mailing_name("P2300R7"));
+
+        assert_eq!(normalize_mailing_name("mailing2024-10"), Some("2024-10"));
+        assert_eq!(normalize_mailing_name("2024-10"), Some("2024-10"));
+        assert_eq!(normalize_mailing_name("mailing"), None);
+        assert_eq!(normalize_mailing_name("LEWG"), None);
+    }
+
+    #[test]
+    fn test_mailing_from_index() {
+        // N4659 is an entry of the real index, P0001R0 has no explicit mailing
+        let database = PaperDatabase::from_json(
+            r#"{
+                "N4659": {
+                    "author": "Richard Smith",
+                    "date": "2017-03-21",
+                    "link": "https://wg21.link/n4659",
+                    "long_link": "https://www.open-std.org/jtc1/sc22/wg21/docs/papers/2017/n4659.pdf",
+                    "mailing": "mailing2017-03",
+                    "title": "Working Draft, Standard for Programming Language C++",
+                    "type": "paper"
+                },
+                "P0001R0": {"type": "paper", "title": "A paper", "date": "2017-03-10"}
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            database.resolve("N4659").unwrap().mailing(),
+            Some("2017-03".to_string())
+        );
+        assert_eq!(database.latest_mailing(), Some("2017-03"));
+        assert_eq!(database.mailing_papers("2017-03", None).len(), 2);
+    }
+
+    #[test]
+    fn test_mailing_papers() {
+        let paper = |date: &str, subgroup: &str| Paper {
+            date: Some(date.to_string()),
+            subgroup: Some(subgroup.to_string()),
+            ..Default::default()
+        };
+
+        let database = PaperDatabase::new(
+            [
+                ("P3001R0", paper("2024-10-10", "LEWG")),
+                ("P3000R1", paper("2024-10-15", "EWG, LEWG")),
+                ("N5000", paper("2024-10-16", "WG21")),
+                ("P2999R0", paper("2024-09-15", "SG1")),
+                ("D3002R0", paper("2024-11-01", "LEWG")),
+                ("CWG1234", paper("2024-11-01", "CWG")),
+            ]
+            .into_iter()
+            .map(|(number, paper)| (number.to_string(), paper))
+            .collect(),
+        );
+
+        let numbers = |papers: Vec<&Paper>| {
+            papers
+                .into_iter()
+                .map(|paper| paper.number.clone().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(database.latest_mailing(), Some("2024-10"));
+        assert_eq!(
+            numbers(database.mailing_papers("2024-10", None)),
+            vec!["N5000", "P3000R1", "P3001R0"]
+        );
+        assert_eq!(
+            numbers(database.mailing_papers("2024-10", Some("lewg"))),
+            vec!["P3000R1", "P3001R0"]
+        );
+        assert!(database.mailing_papers("2024-11", None).is_empty());
+    }
+
+    #[test]
+    fn test_split_authors() {
+        assert_eq!(
+            split_authors("Eric Niebler, Lewis  Baker and Kirk Shoop"),
+            vec!["Eric Niebler", "Lewis Baker", "Kirk Shoop"]
+        );
+        assert_eq!(
+            split_authors("A. Smith; B. Jones & C. Brown, et al."),
+            vec!["A. Smith", "B. Jones", "C. Brown"]
+        );
+        assert!(split_authors("").is_empty());
+    }
+
+    #[test]
+    fn test_normalize_author() {
+        assert_eq!(normalize_author("J.  Smith"), "j smith");
+        assert_eq!(normalize_author("Michał Dominiak"), "michał dominiak");
+    }
+
+    #[test]
+    fn test_author_index() {
+        let paper = |author: &str, date: &str| Paper {
+            author: Some(author.to_string()),
+            date: Some(date.to_string()),
+            ..Default::default()
+        };
+
+        let database = PaperDatabase::new(
+            [
+                ("P1000R0", paper("John Smith", "2020-01-01")),
+                ("P1000R1", paper("John Smith, Jane Smith", "2021-01-01")),
+                (
+                    "P2000R0",
+                    paper("John Smith and Bob Smithson", "2020-06-01"),
+                ),
+                ("N4000", paper("Jane Smith", "2014-01-01")),
+            ]
+            .into_iter()
+            .map(|(number, paper)| (number.to_string(), paper))
+            .collect(),
+        );
+
+        let names = |authors: Vec<&Author>| {
+            authors
+                .into_iter()
+                .map(|author| author.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(database.find_authors("smith")),
+            vec!["Bob Smithson", "Jane Smith", "John Smith"]
+        );
+        assert_eq!(
+            names(database.find_authors("john smith")),
+            vec!["John Smith"]
+        );
+        assert_eq!(
+            names(database.find_authors("J Smith")),
+            vec!["Jane Smith", "John Smith"]
+        );
+        assert!(database.find_authors("nobody").is_empty());
+
+        let john_smith = database.author("john smith").unwrap();
+        let papers: Vec<_> = database
+            .author_papers(john_smith)
+            .into_iter()
+            .map(|paper| paper.number.clone().unwrap())
+            .collect();
+        assert_eq!(papers, vec!["P1000R1", "P2000R0"]);
+    }
+
+    #[test]
+    fn test_search() {
+        let paper = |title: &str, date: &str| Paper {
+            title: Some(title.to_string()),
+            date: Some(date.to_string()),
+            ..Default::default()
+        };
+
+        let database = PaperDatabase::new(
+            [
+                ("P1000R0", paper("Coroutines TS", "2021-05-01")),
+                ("P1001R0", paper("More coroutines", "2023-02-01")),
+                ("P1002R0", paper("Coroutine traits", "2022-07-01")),
+                ("P1003R0", paper("Executors", "2023-01-01")),
+                ("P1004R0", paper("Coroutines (undated)", "")),
+            ]
+            .into_iter()
+            .map(|(number, paper)| (number.to_string(), paper))
+            .collect(),
+        );
+
+        let search = |query: &str, limit: u8| {
+            let (is_limit_reached, papers) =
+                database.search(&query.parse::<SearchQuery>().unwrap(), limit);
+            (
+                is_limit_reached,
+                papers
+                    .into_iter()
+                    .map(|paper| paper.number.clone().unwrap())
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(
+            search("coroutine", 10),
+            (
+                false,
+                vec![
+                    "P1000R0".to_string(),
+                    "P1001R0".to_string(),
+                    "P1002R0".to_string(),
+                    "P1004R0".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            search("coroutine since:2022", 10),
+            (false, vec!["P1001R0".to_string(), "P1002R0".to_string()])
+        );
+        assert_eq!(
+            search("coroutine until:2022-06 sort:date", 10),
+            (false, vec!["P1000R0".to_string()])
+        );
+        assert_eq!(
+            search("year:2023 sort:date", 10),
+            (false, vec!["P1001R0".to_string(), "P1003R0".to_string()])
+        );
+        assert_eq!(
+            search("coroutine sort:date", 2),
+            (true, vec!["P1001R0".to_string(), "P1002R0".to_string()])
+        );
+        assert_eq!(search("(unbalanced", 10), (false, vec![]));
+    }
+
+    #[test]
+    fn test_search_requested_papers() {
+        let database = database(&["P2300R0", "P2300R1", "N4861", "P0847R7"]);
+        let search = |text: &str, limit: u8| {
+            let (is_result_truncated, papers) =
+                database.search_requested_papers(&find_paper_requests(text), limit);
+            (
+                is_result_truncated,
+                papers
+                    .into_iter()
+                    .map(|paper| paper.number.clone().unwrap())
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(
+            search("[P2300] [n4861]", 20),
+            (
+                false,
+                vec![
+                    "P2300R0".to_string(),
+                    "P2300R1".to_string(),
+                    "N4861".to_string()
+                ]
+            )
+        );
+        // The limit is shared by all requests
+        assert_eq!(
+            search("[P2300] [N4861]", 2),
+            (true, vec!["P2300R0".to_string(), "P2300R1".to_string()])
+        );
+        assert_eq!(search("[P9999]", 20), (false, vec![]));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let database = database(&["P2300R0", "P2300R7", "P2300R10", "N4861"]);
+
+        let number = |paper: Option<&Paper>| paper.and_then(|paper| paper.number.clone());
+
+        assert_eq!(
+            number(database.resolve("p2300r7")),
+            Some("P2300R7".to_string())
+        );
+        assert_eq!(
+            number(database.resolve("P2300")),
+            Some("P2300R10".to_string())
+        );
+        assert_eq!(number(database.resolve("n4861")), Some("N4861".to_string()));
+        assert_eq!(number(database.resolve("P2300R3")), None);
+        assert_eq!(number(database.resolve("P1000")), None);
+    }
+
+    #[test]
+    fn test_from_json() {
+        let database = PaperDatabase::from_json(
+            br#"{
+                "P2300R7": {"type": "paper", "title": "std::execution", "author": "Eric Niebler, Lewis Baker", "date": "2023-04-21"},
+                "CWG2518": {"type": "issue", "title": "Conformance requirements and #error/#warning"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(database.len(), 2);
+
+        let paper = database.resolve("P2300R7").unwrap();
+        assert_eq!(paper.number.as_deref(), Some("P2300R7"));
+        assert_eq!(paper.authors, vec!["Eric Niebler", "Lewis Baker"]);
+        assert_eq!(paper.parsed_date, parse_paper_date("2023-04-21"));
+
+        assert!(PaperDatabase::from_json(b"[]").is_err());
+    }
+
+    #[test]
+    fn test_revisions() {
+        let database = database(&["P2300R10", "P2300R2", "P2300R0", "P23000R1"]);
+
+        let revisions: Vec<_> = database
+            .revisions("p2300")
+            .into_iter()
+            .map(|paper| paper.number.clone().unwrap())
+            .collect();
+
+        assert_eq!(revisions, vec!["P2300R0", "P2300R2", "P2300R10"]);
+    }
+}